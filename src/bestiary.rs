@@ -0,0 +1,385 @@
+use crate::{
+    components::{LightKind, TURN_ENERGY},
+    data::{read_ron, validate_entries, DataLoadError},
+    map::biome::Biome,
+    time::{GameClock, Moonphases, Season, TimePeriod},
+    weather::{Weather, WeatherKind},
+};
+use bevy::{
+    asset::{io::Reader, AssetLoader, LoadContext},
+    prelude::*,
+    utils::HashMap,
+};
+use serde::Deserialize;
+use std::hash::Hash;
+
+/// A yokai's name in both of the game's languages.
+#[derive(Clone, Debug, Deserialize)]
+pub struct YokaiName {
+    pub en: String,
+    #[allow(dead_code)]
+    pub ja: String,
+}
+
+/// Special powers a yokai can have. The weather strengthens some and
+/// weakens others; monsters don't otherwise act on them yet.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[allow(dead_code)]
+pub enum Ability {
+    /// Moves through rivers and ponds as easily as on land.
+    Swim,
+    /// Passes over trees, rocks and water.
+    Flight,
+    /// Appears as a person, animal or object.
+    Shapeshift,
+    /// Casts illusions that lead travellers astray.
+    Illusion,
+    /// Breathes or conjures fire.
+    Fire,
+    /// Takes hold of the living.
+    Possession,
+    /// Can't be seen until it chooses to be.
+    Invisibility,
+}
+
+/// One kind of monster, as described in a `.bestiary.ron` file.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Yokai {
+    pub id: String,
+    pub name: YokaiName,
+    #[allow(dead_code)]
+    pub description: String,
+    pub glyphs: Vec<String>,
+    /// sRGB triples, paired with `glyphs` by index.
+    pub colors: Vec<(f32, f32, f32)>,
+    pub hp: u32,
+    pub strength: u32,
+    /// Energy gained each tick; `TURN_ENERGY` acts as often as the player.
+    #[serde(default = "default_speed")]
+    pub speed: i32,
+    /// Relative chance of spawning on a tile of each biome, against the
+    /// other yokai there; biomes that aren't listed never have this yokai.
+    pub habitat: HashMap<Biome, u32>,
+    /// Multipliers on the habitat weight by part of the day, e.g. `Day: 0.0`
+    /// for a yokai that only comes out after dark. Unlisted periods keep 1.
+    #[serde(default)]
+    pub periods: HashMap<TimePeriod, f32>,
+    /// Multipliers by moon phase. Unlisted phases keep 1.
+    #[serde(default)]
+    pub moon: HashMap<Moonphases, f32>,
+    /// Multipliers by season. Unlisted seasons keep 1.
+    #[serde(default)]
+    pub seasons: HashMap<Season, f32>,
+    /// Multipliers by the weather. Unlisted weather keeps 1.
+    #[serde(default)]
+    pub weather: HashMap<WeatherKind, f32>,
+    #[serde(default)]
+    pub abilities: Vec<Ability>,
+    /// Light the yokai gives off wherever it goes, e.g. fox-fire.
+    #[serde(default)]
+    pub light: Option<LightKind>,
+}
+
+fn default_speed() -> i32 {
+    TURN_ENERGY
+}
+
+/// A multiplier from a table keyed on some part of the world's state,
+/// defaulting to no change.
+fn modifier<K: Eq + Hash>(table: &HashMap<K, f32>, key: &K) -> f32 {
+    table.get(key).copied().unwrap_or(1.0)
+}
+
+/// The state of the world that decides which yokai are abroad.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SpawnConditions {
+    pub period: TimePeriod,
+    pub moon: Moonphases,
+    pub season: Season,
+    pub weather: WeatherKind,
+    /// How much the weather draws yokai out, from `Weather::spawn_multiplier`.
+    pub weather_activity: f32,
+}
+
+impl SpawnConditions {
+    pub fn now(clock: &GameClock, weather: &Weather) -> Self {
+        Self {
+            period: clock.period(),
+            moon: clock.moon_phase(),
+            season: clock.season(),
+            weather: weather.dominant(),
+            weather_activity: weather.spawn_multiplier(),
+        }
+    }
+
+    /// How many yokai roam at once, relative to a quiet, clear night.
+    pub fn activity(&self) -> f32 {
+        self.moon.yokai_activity() * self.weather_activity
+    }
+}
+
+impl Yokai {
+    /// Relative chance of appearing on a tile of `biome`; zero means never.
+    pub fn habitat_weight(&self, biome: Biome) -> u32 {
+        self.habitat.get(&biome).copied().unwrap_or(0)
+    }
+
+    /// How much the yokai is about under `conditions`, whatever the biome;
+    /// zero while it keeps away.
+    pub fn activity(&self, conditions: &SpawnConditions) -> f32 {
+        modifier(&self.periods, &conditions.period)
+            * modifier(&self.moon, &conditions.moon)
+            * modifier(&self.seasons, &conditions.season)
+            * modifier(&self.weather, &conditions.weather)
+    }
+
+    /// Relative chance of this yokai being the one to appear on a tile of
+    /// `biome` under `conditions`; zero means it can't.
+    pub fn spawn_weight(&self, biome: Biome, conditions: &SpawnConditions) -> f32 {
+        self.habitat_weight(biome) as f32 * self.activity(conditions)
+    }
+
+    /// Number of glyph and color variants the yokai can be drawn with.
+    pub fn variant_count(&self) -> usize {
+        self.glyphs.len().min(self.colors.len())
+    }
+
+    pub fn glyph(&self, variant: usize) -> &str {
+        &self.glyphs[variant % self.glyphs.len()]
+    }
+
+    pub fn color(&self, variant: usize) -> Color {
+        let (r, g, b) = self.colors[variant % self.colors.len()];
+        Color::srgb(r, g, b)
+    }
+}
+
+/// Every yokai the game knows, in the order they appear in the asset, so
+/// spawning through them stays reproducible for a seed.
+#[derive(Asset, Resource, TypePath, Clone)]
+pub struct Bestiary {
+    yokai: Vec<Yokai>,
+}
+
+impl Bestiary {
+    /// A bestiary of `yokai`, as long as each has a unique id and something
+    /// to be drawn with.
+    pub fn new(yokai: Vec<Yokai>) -> Result<Self, DataLoadError> {
+        validate_entries(
+            yokai
+                .iter()
+                .map(|yokai| (yokai.id.as_str(), yokai.glyphs.len(), yokai.colors.len())),
+        )?;
+        Ok(Self { yokai })
+    }
+
+    /// The bestiary shipped in `assets/data`, for tests that need real yokai.
+    #[cfg(test)]
+    pub(crate) fn base() -> Self {
+        let yokai = ron::from_str(include_str!("../assets/data/base.bestiary.ron"))
+            .expect("base bestiary should parse");
+        Self::new(yokai).expect("base bestiary should be valid")
+    }
+
+    /// Every yokai that can appear on a tile of `biome` under `conditions`,
+    /// with its relative chance.
+    pub fn spawn_table(&self, biome: Biome, conditions: &SpawnConditions) -> Vec<(&Yokai, f32)> {
+        self.yokai
+            .iter()
+            .map(|yokai| (yokai, yokai.spawn_weight(biome, conditions)))
+            .filter(|&(_, weight)| weight > 0.0)
+            .collect()
+    }
+
+    /// Look a yokai up by id or by English name, as map files may use either.
+    pub fn get(&self, name: &str) -> Option<&Yokai> {
+        self.yokai
+            .iter()
+            .find(|yokai| yokai.id == name || yokai.name.en == name)
+    }
+}
+
+/// Loads a `Bestiary` from a RON list of yokai.
+#[derive(Default)]
+pub struct BestiaryLoader;
+
+impl AssetLoader for BestiaryLoader {
+    type Asset = Bestiary;
+    type Settings = ();
+    type Error = DataLoadError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Bestiary, DataLoadError> {
+        Bestiary::new(read_ron(reader).await?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["bestiary.ron"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A clear spring night under a quarter moon, when no table changes how
+    /// much any yokai is about.
+    fn night() -> SpawnConditions {
+        SpawnConditions {
+            period: TimePeriod::Night,
+            moon: Moonphases::FirstQuarter,
+            season: Season::Spring,
+            weather: WeatherKind::Clear,
+            weather_activity: 1.0,
+        }
+    }
+
+    #[test]
+    fn yokai_are_found_by_id_or_english_name() {
+        let bestiary = Bestiary::base();
+        assert_eq!(bestiary.get("yuki_onna").unwrap().name.en, "Yuki-onna");
+        assert_eq!(bestiary.get("Yuki-onna").unwrap().id, "yuki_onna");
+        assert_eq!(bestiary.get("Oni").unwrap().id, "oni");
+        assert!(bestiary.get("雪女").is_none());
+        assert!(bestiary.get("nue").is_none());
+    }
+
+    #[test]
+    fn unlisted_fields_take_their_defaults() {
+        let bestiary = Bestiary::base();
+        let goblin = bestiary.get("goblin").unwrap();
+        assert_eq!(goblin.speed, TURN_ENERGY);
+        assert!(goblin.abilities.is_empty() && goblin.light.is_none());
+        assert_eq!(goblin.activity(&night()), 1.0);
+        assert_eq!(goblin.habitat_weight(Biome::River), 0);
+        assert_eq!(bestiary.get("tengu").unwrap().speed, 140);
+    }
+
+    #[test]
+    fn activity_multiplies_every_table() {
+        let bestiary = Bestiary::base();
+        let activity =
+            |id: &str, conditions: SpawnConditions| bestiary.get(id).unwrap().activity(&conditions);
+
+        let day = SpawnConditions {
+            period: TimePeriod::Day,
+            ..night()
+        };
+        assert_eq!(activity("oni", day), 0.0);
+        let autumn_midnight = SpawnConditions {
+            period: TimePeriod::WitchingHour,
+            season: Season::Autumn,
+            ..night()
+        };
+        assert_eq!(activity("oni", autumn_midnight), 3.0);
+
+        let full_moon_dusk = SpawnConditions {
+            period: TimePeriod::Dusk,
+            moon: Moonphases::Full,
+            ..night()
+        };
+        assert_eq!(activity("kitsune", full_moon_dusk), 4.0);
+
+        let snowy_winter_day = SpawnConditions {
+            period: TimePeriod::Day,
+            season: Season::Winter,
+            weather: WeatherKind::Snow,
+            ..night()
+        };
+        assert_eq!(activity("yuki_onna", snowy_winter_day), 0.5);
+        assert_eq!(activity("yuki_onna", night()), 0.0);
+    }
+
+    #[test]
+    fn spawn_tables_weight_habitat_by_activity() {
+        let bestiary = Bestiary::base();
+        let table = |biome, conditions| {
+            bestiary
+                .spawn_table(biome, &conditions)
+                .into_iter()
+                .map(|(yokai, weight)| (yokai.id.as_str(), weight))
+                .collect::<Vec<_>>()
+        };
+
+        // Tengu keep off the night and Yuki-onna out of spring
+        assert_eq!(
+            table(Biome::Forest, night()),
+            [
+                ("oni", 2.0),
+                ("goblin", 3.0),
+                ("kitsune", 2.0),
+                ("tanuki", 3.0)
+            ]
+        );
+        let new_moon = SpawnConditions {
+            moon: Moonphases::New,
+            ..night()
+        };
+        assert_eq!(
+            table(Biome::Ruins, new_moon),
+            [("oni", 3.0), ("goblin", 4.0), ("yurei", 6.0)]
+        );
+        let storm = SpawnConditions {
+            period: TimePeriod::Day,
+            weather: WeatherKind::Storm,
+            ..night()
+        };
+        assert_eq!(table(Biome::Pond, storm), [("kappa", 7.5)]);
+    }
+
+    #[test]
+    fn the_moon_and_weather_set_how_many_roam() {
+        let stormy_full_moon = SpawnConditions {
+            moon: Moonphases::Full,
+            weather_activity: 1.25,
+            ..night()
+        };
+        assert_eq!(stormy_full_moon.activity(), 1.875);
+        assert_eq!(night().activity(), 1.0);
+    }
+
+    /// The loader's error for a bestiary of `entries`.
+    fn load_error(entries: &str) -> String {
+        let yokai: Vec<Yokai> = ron::from_str(entries).unwrap();
+        match Bestiary::new(yokai) {
+            Err(DataLoadError::Invalid(message)) => message,
+            Err(err) => panic!("unexpected error: {err}"),
+            Ok(_) => panic!("loaded an invalid bestiary"),
+        }
+    }
+
+    fn entry(id: &str, glyphs: &str) -> String {
+        format!(
+            r#"(
+                id: "{id}",
+                name: (en: "{id}", ja: "{id}"),
+                description: "",
+                glyphs: {glyphs},
+                colors: [(1.0, 1.0, 1.0)],
+                hp: 1,
+                strength: 1,
+                habitat: {{}},
+            )"#
+        )
+    }
+
+    #[test]
+    fn the_loader_rejects_duplicates_and_yokai_without_glyphs() {
+        let duplicate = format!(
+            "[{}, {}]",
+            entry("oni", r#"["鬼"]"#),
+            entry("oni", r#"["鬼"]"#)
+        );
+        assert_eq!(load_error(&duplicate), "`oni` is defined twice");
+
+        let blank = format!("[{}]", entry("nue", "[]"));
+        assert_eq!(
+            load_error(&blank),
+            "`nue` needs at least one glyph and one color"
+        );
+    }
+}
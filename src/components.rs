@@ -1,4 +1,57 @@
+use crate::{bestiary::Ability, map::terrain::Stairs, time::Season};
 use bevy::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+
+/// Anything shorter than this, in tiles, only partly blocks sight.
+const EYE_LEVEL: f32 = 1.0;
+/// Tiles an emissive material's light reaches per unit of emission.
+const EMISSION_REACH: f32 = 6.0;
+
+/// How a tile meets light and sight. Field of view and lighting both read it,
+/// so what the player can see and what a lantern can reach agree.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Material {
+    /// How much the tile blocks sight and light, from 0 (clear) to 1 (opaque).
+    pub opacity: f32,
+    /// How tall whatever stands on the tile is, in tiles.
+    pub height: f32,
+    /// Light the tile gives off by itself, per sRGB channel.
+    pub emission: Vec3,
+    /// Share of the light from lanterns and fires that the surface gives
+    /// back; dark, wet ground returns less than pale stone.
+    pub reflectance: f32,
+}
+
+impl Material {
+    /// A plain material: opaque and a tile tall if solid, otherwise clear
+    /// and flat.
+    pub fn new(solid: bool) -> Self {
+        Self {
+            opacity: if solid { 1.0 } else { 0.0 },
+            height: if solid { 1.0 } else { 0.0 },
+            emission: Vec3::ZERO,
+            reflectance: 1.0,
+        }
+    }
+
+    /// How much the tile stops sight and light passing over it: its opacity,
+    /// reduced for anything below eye level.
+    pub fn sight_blocking(&self) -> f32 {
+        self.opacity * (self.height / EYE_LEVEL).min(1.0)
+    }
+
+    /// The light an emissive material casts around itself, reaching further
+    /// the brighter it glows.
+    pub fn glow(&self) -> Option<LightSource> {
+        let brightness = self.emission.max_element();
+        (brightness > 0.0).then(|| LightSource {
+            color: self.emission,
+            radius: (brightness * EMISSION_REACH).ceil() as i32,
+            fire: false,
+        })
+    }
+}
 
 #[derive(Component, Clone)]
 pub struct MapItem {
@@ -6,26 +59,209 @@ pub struct MapItem {
     pub color_variants: Vec<Color>,
     pub current_variant: usize,
     pub solid: bool,
+    pub water: bool,
+    pub stairs: Option<Stairs>,
+    pub material: Material,
+    /// Turns it takes to step onto the tile.
+    pub movement_cost: f32,
+    /// Whether the player has ever seen the tile, so it can be drawn from
+    /// memory once out of sight.
+    pub explored: bool,
+    /// Id of the terrain the tile is, for looking up its seasonal looks.
+    pub terrain: String,
+    /// The season whose look the tile wears, or `None` for its usual one.
+    pub season: Option<Season>,
 }
 
 impl MapItem {
-    pub fn new(characters: Vec<String>, colors: Vec<Color>, solid: bool) -> Self {
+    pub fn new(
+        characters: Vec<String>,
+        colors: Vec<Color>,
+        solid: bool,
+        water: bool,
+        stairs: Option<Stairs>,
+        rng: &mut impl Rng,
+    ) -> Self {
         let variant_count = characters.len().min(colors.len());
         assert!(variant_count > 0, "Must provide at least one variant");
         Self {
             character_variants: characters,
             color_variants: colors,
-            current_variant: rand::random::<usize>() % variant_count,
+            current_variant: rng.gen_range(0..variant_count),
             solid,
+            water,
+            stairs,
+            material: Material::new(solid),
+            movement_cost: 1.0,
+            explored: false,
+            terrain: String::new(),
+            season: None,
         }
     }
 
+    /// The tile's glyph. A seasonal look may have fewer variants than the
+    /// usual one, so the variant wraps rather than being lost when it's worn.
     pub fn current_character(&self) -> &str {
-        &self.character_variants[self.current_variant]
+        &self.character_variants[self.current_variant % self.character_variants.len()]
     }
 
     pub fn current_color(&self) -> Color {
-        self.color_variants[self.current_variant]
+        self.color_variants[self.current_variant % self.color_variants.len()]
+    }
+}
+
+/// The color a world glyph has in full daylight. Lighting tints it into the
+/// glyph's `TextColor`; UI text has none and is left alone.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct BaseColor(pub Color);
+
+/// Kinds of light found about the world, each with its own color and reach.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub enum LightKind {
+    /// A paper lantern (提灯), carried or hung at a shrine.
+    Lantern,
+    Torch,
+    /// A shrine or temple brazier; the brightest fire.
+    Brazier,
+    /// Fox-fire (狐火), the cold blue flames that kitsune conjure.
+    Kitsunebi,
+}
+
+impl LightKind {
+    /// Parse a light kind from its lowercase name, as written in map files.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "lantern" => Some(LightKind::Lantern),
+            "torch" => Some(LightKind::Torch),
+            "brazier" => Some(LightKind::Brazier),
+            "kitsunebi" => Some(LightKind::Kitsunebi),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LightKind::Lantern => "lantern",
+            LightKind::Torch => "torch",
+            LightKind::Brazier => "brazier",
+            LightKind::Kitsunebi => "kitsunebi",
+        }
+    }
+
+    pub fn source(self) -> LightSource {
+        let (color, radius) = match self {
+            LightKind::Lantern => (Vec3::new(1.0, 0.75, 0.4), 5),
+            LightKind::Torch => (Vec3::new(1.0, 0.6, 0.25), 6),
+            LightKind::Brazier => (Vec3::new(1.0, 0.5, 0.2), 8),
+            LightKind::Kitsunebi => (Vec3::new(0.4, 0.7, 1.0), 3),
+        };
+        let fire = self != LightKind::Kitsunebi;
+        LightSource {
+            color,
+            radius,
+            fire,
+        }
+    }
+}
+
+/// Casts colored light over the tiles around the entity's grid position,
+/// fading with distance and stopped by opaque terrain.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct LightSource {
+    /// Light added at the source itself, per sRGB channel.
+    pub color: Vec3,
+    /// Tiles the light reaches.
+    pub radius: i32,
+    /// Whether the light is a real flame, which rain damps down. Fox-fire and
+    /// glowing stone burn on regardless.
+    pub fire: bool,
+}
+
+/// Logical location on the terrain grid and the source of truth for game
+/// rules. `Transform` is derived from it by `sync_grid_transforms`.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct GridPosition {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl GridPosition {
+    pub fn new(x: i32, y: i32) -> Self {
+        Self { x, y }
+    }
+
+    pub fn offset(self, delta: IVec2) -> Self {
+        Self::new(self.x + delta.x, self.y + delta.y)
+    }
+}
+
+impl From<IVec2> for GridPosition {
+    fn from(pos: IVec2) -> Self {
+        Self::new(pos.x, pos.y)
+    }
+}
+
+impl From<GridPosition> for IVec2 {
+    fn from(pos: GridPosition) -> Self {
+        IVec2::new(pos.x, pos.y)
+    }
+}
+
+/// Energy an actor needs to take a turn, which is also what an ordinary
+/// step costs. An actor with this speed acts once a tick.
+pub const TURN_ENERGY: i32 = 100;
+
+/// Something an actor spends its turn on.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    /// A step onto a tile that takes `cost` turns to enter.
+    Move {
+        cost: f32,
+    },
+    Attack,
+    Wait,
+    UseItem,
+}
+
+impl Action {
+    /// Energy the action takes. Every action takes some, so even a step onto
+    /// terrain that costs nothing lets time pass.
+    pub fn energy(self) -> i32 {
+        let energy = match self {
+            Action::Move { cost } => (TURN_ENERGY as f32 * cost).round() as i32,
+            Action::Attack => TURN_ENERGY * 6 / 5,
+            Action::Wait => TURN_ENERGY / 2,
+            Action::UseItem => TURN_ENERGY * 3 / 2,
+        };
+        energy.max(1)
+    }
+}
+
+/// Takes turns from the scheduler: gains `speed` energy every tick and may
+/// act whenever it has `TURN_ENERGY` saved up, paying for what it does.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Actor {
+    pub speed: i32,
+    pub energy: i32,
+}
+
+impl Actor {
+    /// An actor that has to wait for its first turn.
+    pub fn new(speed: i32) -> Self {
+        Self { speed, energy: 0 }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.energy >= TURN_ENERGY
+    }
+
+    /// Let a tick pass, saving up energy.
+    pub fn recover(&mut self) {
+        self.energy += self.speed.max(1);
+    }
+
+    pub fn spend(&mut self, action: Action) {
+        self.energy -= action.energy();
     }
 }
 
@@ -78,10 +314,19 @@ pub struct PlayerWeapon;
 #[derive(Component)]
 pub struct Monster {
     pub hp: u32,
+    #[allow(dead_code)]
     pub max_hp: u32,
     pub strength: u32,
     pub name: String,
     pub is_alive: bool,
+    /// The yokai's powers, which the weather can strengthen or weaken.
+    pub abilities: Vec<Ability>,
+}
+
+/// Something lying on the ground, placed by a map file.
+#[derive(Component)]
+pub struct Item {
+    pub name: String,
 }
 
 #[derive(Component)]
@@ -91,3 +336,59 @@ pub struct CombatMessage {
 
 #[derive(Component)]
 pub struct SpawnMonstersMarker;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn actions_cost_energy_by_kind() {
+        assert_eq!(Action::Move { cost: 1.0 }.energy(), TURN_ENERGY);
+        assert_eq!(Action::Move { cost: 1.5 }.energy(), 150);
+        assert_eq!(Action::Move { cost: 0.004 }.energy(), 1);
+        assert_eq!(Action::Move { cost: 0.0 }.energy(), 1);
+        assert_eq!(Action::Move { cost: -2.0 }.energy(), 1);
+        assert_eq!(Action::Move { cost: f32::NAN }.energy(), 1);
+        assert_eq!(Action::Attack.energy(), 120);
+        assert_eq!(Action::Wait.energy(), 50);
+        assert_eq!(Action::UseItem.energy(), 150);
+    }
+
+    #[test]
+    fn actors_save_up_energy_to_act() {
+        let mut actor = Actor::new(TURN_ENERGY);
+        assert!(!actor.is_ready());
+        actor.recover();
+        assert!(actor.is_ready());
+        actor.spend(Action::Attack);
+        assert_eq!(actor.energy, -20);
+        actor.recover();
+        assert!(!actor.is_ready());
+
+        // Even a stopped actor gets a turn eventually
+        let mut stuck = Actor::new(0);
+        for _ in 0..TURN_ENERGY {
+            stuck.recover();
+        }
+        assert!(stuck.is_ready());
+    }
+
+    #[test]
+    fn faster_actors_act_more_often() {
+        let turns = |speed| {
+            let mut actor = Actor::new(speed);
+            let mut turns = 0;
+            for _ in 0..100 {
+                actor.recover();
+                while actor.is_ready() {
+                    actor.spend(Action::Move { cost: 1.0 });
+                    turns += 1;
+                }
+            }
+            turns
+        };
+        assert_eq!(turns(TURN_ENERGY), 100);
+        assert_eq!(turns(140), 140);
+        assert_eq!(turns(70), 70);
+    }
+}
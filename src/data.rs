@@ -0,0 +1,64 @@
+use bevy::asset::io::Reader;
+use serde::de::DeserializeOwned;
+use std::fmt;
+
+/// Why a RON data asset (terrain, bestiary) failed to load.
+#[derive(Debug)]
+pub enum DataLoadError {
+    Io(std::io::Error),
+    Ron(ron::error::SpannedError),
+    /// An entry that parsed but can't be used, e.g. one without glyphs.
+    Invalid(String),
+}
+
+impl fmt::Display for DataLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DataLoadError::Io(err) => write!(f, "could not read data file: {}", err),
+            DataLoadError::Ron(err) => write!(f, "malformed data file: {}", err),
+            DataLoadError::Invalid(message) => write!(f, "invalid entry: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for DataLoadError {}
+
+impl From<std::io::Error> for DataLoadError {
+    fn from(err: std::io::Error) -> Self {
+        DataLoadError::Io(err)
+    }
+}
+
+impl From<ron::error::SpannedError> for DataLoadError {
+    fn from(err: ron::error::SpannedError) -> Self {
+        DataLoadError::Ron(err)
+    }
+}
+
+/// Read a whole asset and parse it as RON.
+pub async fn read_ron<T: DeserializeOwned>(reader: &mut dyn Reader) -> Result<T, DataLoadError> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes).await?;
+    Ok(ron::de::from_bytes(&bytes)?)
+}
+
+/// Check that every id in a data file is unique and that each entry has at
+/// least one glyph and one color to draw with.
+pub fn validate_entries<'a>(
+    entries: impl IntoIterator<Item = (&'a str, usize, usize)>,
+) -> Result<(), DataLoadError> {
+    let mut seen = Vec::new();
+    for (id, glyphs, colors) in entries {
+        if glyphs == 0 || colors == 0 {
+            return Err(DataLoadError::Invalid(format!(
+                "`{}` needs at least one glyph and one color",
+                id
+            )));
+        }
+        if seen.contains(&id) {
+            return Err(DataLoadError::Invalid(format!("`{}` is defined twice", id)));
+        }
+        seen.push(id);
+    }
+    Ok(())
+}
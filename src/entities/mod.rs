@@ -2,13 +2,12 @@ mod monster;
 mod player;
 mod terrain;
 mod text;
-mod time;
 
 pub use monster::Monster;
 pub use player::Player;
 pub use terrain::{Land, LandInstance, LAND_DIRT, LAND_TREE};
+pub use crate::time::*;
 pub use text::*;
-pub use time::*;
 
 use colored::Colorize;
 const WITCHING_HOUR: usize = TICKS_PER_DAY / 6;
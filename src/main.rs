@@ -2,30 +2,103 @@ use bevy::{
     prelude::*,
     window::{PrimaryWindow, WindowResolution},
 };
-use rand::seq::SliceRandom;
+use std::path::Path;
+mod bestiary;
 mod components;
+mod data;
 mod map;
+mod seed;
 mod systems;
+mod time;
+mod weather;
 
+use bestiary::{Bestiary, BestiaryLoader};
 use components::*;
+use map::{
+    generator::{level_seed, MapGeneratorRegistry},
+    terrain::{TerrainCatalog, TerrainCatalogLoader},
+};
+use seed::{WorldRng, WorldSeed};
 use systems::*;
+use time::{DayChanged, GameClock, PeriodChanged, PlayerActed, SeasonChanged, TimePassed};
+use weather::{Weather, WeatherChanged};
 
 fn main() {
+    let world_seed = WorldSeed::from_env();
+    println!("World seed: {}", world_seed.0);
+
     App::new()
         .add_systems(Startup, setup_audio)
         .add_plugins(DefaultPlugins.set(WindowPlugin {
             primary_window: Some(Window {
-                resizable: false,
+                resizable: true,
                 resolution: WindowResolution::new(800.0, 600.0),
                 ..default()
             }),
             ..default()
         }))
         .insert_resource(ClearColor(Color::srgb(0.2, 0.1, 0.05)))
+        .insert_resource(world_seed)
+        .insert_resource(WorldRng::new(world_seed))
+        .init_resource::<CurrentLevel>()
+        .init_resource::<LevelStore>()
+        .init_resource::<GameClock>()
+        .init_resource::<AmbientTint>()
+        .init_resource::<FieldOfView>()
+        .init_resource::<LightMap>()
+        .init_resource::<Weather>()
+        .add_event::<PlayerActed>()
+        .add_event::<TimePassed>()
+        .add_event::<PeriodChanged>()
+        .add_event::<DayChanged>()
+        .add_event::<SeasonChanged>()
+        .add_event::<WeatherChanged>()
+        .insert_resource(MapGeneratorRegistry::from_assets(Path::new("assets")))
+        .init_asset::<TerrainCatalog>()
+        .init_asset_loader::<TerrainCatalogLoader>()
+        .init_asset::<Bestiary>()
+        .init_asset_loader::<BestiaryLoader>()
         .init_state::<GameState>()
-        .add_systems(Startup, setup_intro)
-        .add_systems(OnEnter(GameState::Playing), (setup, spawn_player))
-        .add_systems(Update, spawn_monsters)
+        .add_systems(Startup, (setup_intro, load_data_assets))
+        .add_systems(
+            Update,
+            (
+                sync_data_asset::<TerrainCatalog>,
+                sync_data_asset::<Bestiary>,
+            ),
+        )
+        .add_systems(OnEnter(GameState::Playing), (setup, spawn_player).chain())
+        .add_systems(
+            Update,
+            (update_precipitation, flash_lightning)
+                .after(camera_follow_player)
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(
+            Update,
+            (leave_tracks, storm_events)
+                .after(update_weather)
+                .before(update_lights)
+                .run_if(in_state(GameState::Playing)),
+        )
+        // Everything drawing from the WorldRng runs in one fixed order:
+        // run_turns, update_weather, storm_events, then the spawners. These
+        // also wait for the chunks a new level streams in, since yokai only
+        // spawn on tiles the player can reach
+        .add_systems(
+            Update,
+            (spawn_monsters, spawn_placements, respawn_monsters)
+                .chain()
+                .after(storm_events)
+                .after(stream_chunks)
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(
+            Update,
+            reskin_terrain
+                .after(stream_chunks)
+                .run_if(in_state(GameState::Playing)),
+        )
         .add_systems(
             Update,
             (
@@ -38,12 +111,29 @@ fn main() {
         .add_systems(
             Update,
             (
-                player_movement,
+                (player_movement, run_turns).chain(),
+                tick_clock,
+                announce_time,
+                update_weather,
+                announce_weather,
+                update_ambient,
+                use_stairs,
+                cycle_level_generator,
+                follow_player_weapon,
+                sync_grid_transforms,
+                update_lights,
+                update_fov,
+                apply_fov,
+                apply_ambient,
+                camera_follow_player,
+                stream_chunks,
+                anchor_combat_message,
                 toggle_inventory,
                 render_inventory.run_if(|state: Option<Res<InventoryState>>| state.is_some()),
                 cleanup_dead_monsters,
             )
-                .chain(),
+                .chain()
+                .run_if(in_state(GameState::Playing)),
         )
         .run();
 }
@@ -78,57 +168,76 @@ pub struct MainCamera;
 
 fn setup(
     mut commands: Commands,
+    world_seed: Res<WorldSeed>,
+    mut level_store: ResMut<LevelStore>,
+    registry: Res<MapGeneratorRegistry>,
+    terrain: Res<TerrainCatalog>,
     asset_server: Res<AssetServer>,
     window_query: Query<&Window, With<PrimaryWindow>>,
 ) {
     // Camera with explicit order
-    commands.spawn((
-        Camera2d::default(),
-        Camera {
-            order: 1,
-            ..default()
-        },
-        MainCamera,
-    ));
+    let camera = commands
+        .spawn((
+            Camera2d,
+            Camera {
+                order: 1,
+                ..default()
+            },
+            MainCamera,
+        ))
+        .id();
 
     // Load the font
     let font = asset_server.load("fonts/NotoSansJP-VariableFont_wght.ttf");
 
     // Get window dimensions
     let window = window_query.single();
-    let width = window.width();
     let height = window.height();
 
-    // Character size and spacing
+    // Character size
     let char_size = 12.0;
-    let spacing = char_size;
-
-    // Calculate starting position (top-left corner)
-    let start_x = -width / 2.0 + spacing / 2.0;
-    let start_y = height / 2.0 - spacing / 2.0;
 
-    // Generate terrain first
-    map::generation::generate_terrain(&mut commands, font.clone(), width, height, char_size);
+    // Generate the terrain around the origin first; the rest of the
+    // overworld streams in as the player explores
+    let mut tile_map = enter_level(
+        &mut level_store,
+        &registry,
+        &terrain,
+        *world_seed,
+        0,
+        char_size,
+    );
+    load_chunks_around(
+        &mut commands,
+        &mut tile_map,
+        registry.generator_for_depth(0),
+        &terrain,
+        level_seed(world_seed.0, 0),
+        &font,
+        IVec2::ZERO,
+        half_view_tiles(window, char_size),
+    );
+    commands.insert_resource(tile_map);
 
     // We'll spawn monsters in the next frame when terrain is ready
     commands.spawn_empty().insert(SpawnMonstersMarker);
 
-    // Combat message will be spawned later when needed
-
-    // Spawn combat message bar
-    commands.spawn((
-        create_text_color_bundle(
-            font.clone(),
-            "",
-            0.0,                  // Left edge + small margin
-            -height / 2.0 + 12.0, // Bottom edge + small margin
-            2.0,
-            Color::srgb(0.8, 0.8, 0.8),
-        ),
-        CombatMessage {
-            message: String::new(),
-        },
-    ));
+    // Spawn combat message bar, parented to the camera so it stays on screen
+    commands.entity(camera).with_children(|parent| {
+        parent.spawn((
+            create_text_color_bundle(
+                font.clone(),
+                "",
+                0.0,                  // Left edge + small margin
+                -height / 2.0 + 12.0, // Bottom edge + small margin
+                2.0,
+                Color::srgb(0.8, 0.8, 0.8),
+            ),
+            CombatMessage {
+                message: String::new(),
+            },
+        ));
+    });
 }
 
 fn create_text_color_bundle(
@@ -139,14 +248,14 @@ fn create_text_color_bundle(
     z: f32,
     color: Color,
 ) -> (Text2d, TextFont, Transform, TextColor) {
-    return (
+    (
         Text2d::new(text),
         TextFont {
-            font: font,
+            font,
             font_size: 12.0,
             ..default()
         },
         Transform::from_xyz(x, y, z),
         TextColor::from(color),
-    );
+    )
 }
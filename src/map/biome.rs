@@ -0,0 +1,79 @@
+use serde::Deserialize;
+
+/// Broad region of the overworld, picked from elevation and moisture noise.
+/// `River` and `Pond` are carved afterwards by `hydrology`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+pub enum Biome {
+    Forest,
+    BambooGrove,
+    Marsh,
+    River,
+    Pond,
+    MountainSlope,
+    RicePaddy,
+    /// Cave and ruin dungeons below the overworld.
+    Ruins,
+}
+
+impl Biome {
+    /// Parse a biome from its variant name, as written in map files.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let biome = match name {
+            "Forest" => Biome::Forest,
+            "BambooGrove" => Biome::BambooGrove,
+            "Marsh" => Biome::Marsh,
+            "River" => Biome::River,
+            "Pond" => Biome::Pond,
+            "MountainSlope" => Biome::MountainSlope,
+            "RicePaddy" => Biome::RicePaddy,
+            "Ruins" => Biome::Ruins,
+            _ => return None,
+        };
+        Some(biome)
+    }
+
+    /// Pick a biome from normalized `[0, 1]` elevation and moisture.
+    pub fn classify(elevation: f64, moisture: f64) -> Self {
+        match (elevation, moisture) {
+            (e, _) if e > 0.72 => Biome::MountainSlope,
+            (e, _) if e < 0.25 => Biome::Marsh,
+            (e, m) if e < 0.45 && m > 0.62 => Biome::RicePaddy,
+            (_, m) if m > 0.62 => Biome::BambooGrove,
+            _ => Biome::Forest,
+        }
+    }
+
+    /// Id of the terrain for a tile in this biome. `detail` is a normalized
+    /// noise value giving variation within the biome, and `elevation`
+    /// separates peaks from the slopes below them.
+    pub fn terrain(&self, detail: f64, elevation: f64) -> &'static str {
+        match self {
+            Biome::Forest => match detail {
+                n if n < 0.3 => "earth", // Clearings/paths (30%)
+                n if n < 0.6 => "grass", // Forest floor (30%)
+                n if n < 0.9 => "tree",  // Dense forest (30%)
+                _ => "rock",             // Rocky outcrops (10%)
+            },
+            Biome::BambooGrove => match detail {
+                n if n < 0.55 => "bamboo",
+                _ => "grass",
+            },
+            Biome::Marsh => match detail {
+                n if n < 0.6 => "marsh",
+                _ => "grass",
+            },
+            Biome::River => "river",
+            Biome::Pond => "pond",
+            Biome::MountainSlope => match elevation {
+                e if e > 0.82 => "mountain",
+                _ if detail > 0.85 => "rock",
+                _ => "slope",
+            },
+            Biome::RicePaddy => match detail {
+                n if n < 0.85 => "rice_paddy",
+                _ => "earth",
+            },
+            Biome::Ruins => "dungeon_floor",
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use super::biome::Biome;
+use super::tile_grid::TileGrid;
+use crate::components::MapItem;
+use bevy::prelude::*;
+
+/// Width and height of a chunk in tiles.
+pub const CHUNK_SIZE: i32 = 16;
+
+/// Terrain for one `CHUNK_SIZE`² block of the map. The tile data outlives the
+/// spawned entities, so a chunk that is streamed out keeps its state.
+#[derive(Clone)]
+pub struct Chunk {
+    pub tiles: Vec<MapItem>,
+    pub biomes: Vec<Biome>,
+    /// Entities rendering each tile, present only while the chunk is spawned.
+    pub entities: Option<Vec<Entity>>,
+}
+
+impl Chunk {
+    pub fn new(tiles: Vec<MapItem>, biomes: Vec<Biome>) -> Self {
+        debug_assert_eq!(tiles.len(), (CHUNK_SIZE * CHUNK_SIZE) as usize);
+        debug_assert_eq!(biomes.len(), tiles.len());
+        Self {
+            tiles,
+            biomes,
+            entities: None,
+        }
+    }
+
+    /// Take the tiles of a chunk-sized grid, e.g. from
+    /// `MapGenerator::generate_chunk`.
+    pub fn from_grid(grid: TileGrid) -> Self {
+        let (tiles, biomes) = grid.into_parts();
+        Self::new(tiles, biomes)
+    }
+
+    pub fn is_spawned(&self) -> bool {
+        self.entities.is_some()
+    }
+}
+
+/// Chunk coordinate containing a tile position.
+pub fn chunk_of(pos: IVec2) -> IVec2 {
+    IVec2::new(pos.x.div_euclid(CHUNK_SIZE), pos.y.div_euclid(CHUNK_SIZE))
+}
+
+/// Tile position of a chunk's top-left corner.
+pub fn chunk_origin(chunk: IVec2) -> IVec2 {
+    chunk * CHUNK_SIZE
+}
+
+/// Index of a tile position within its chunk's `tiles`.
+pub fn local_index(pos: IVec2) -> usize {
+    let local = pos - chunk_origin(chunk_of(pos));
+    (local.y * CHUNK_SIZE + local.x) as usize
+}
+
+/// Tile positions covered by a chunk, in the same order as `Chunk::tiles`.
+pub fn chunk_positions(chunk: IVec2) -> impl Iterator<Item = IVec2> {
+    let origin = chunk_origin(chunk);
+    (0..CHUNK_SIZE).flat_map(move |y| (0..CHUNK_SIZE).map(move |x| origin + IVec2::new(x, y)))
+}
+
+/// Derive a per-chunk seed so each chunk generates identically regardless of
+/// the order chunks are visited in (splitmix64 finaliser).
+pub fn chunk_seed(seed: u64, chunk: IVec2) -> u64 {
+    let key = ((chunk.x as u32 as u64) << 32) | chunk.y as u32 as u64;
+    let mut z = seed.wrapping_add(key.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
@@ -0,0 +1,204 @@
+use super::tile_grid::TileGrid;
+use crate::components::MapItem;
+use bevy::{prelude::*, utils::HashSet};
+
+/// Walkable pockets smaller than this are filled in rather than connected.
+const MIN_REGION_SIZE: usize = 4;
+
+const NEIGHBOURS: [IVec2; 4] = [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y];
+
+/// Every position reachable from `start` by orthogonal steps onto positions
+/// that are `passable`, including `start` itself if it is passable.
+pub fn flood_fill(start: IVec2, passable: impl Fn(IVec2) -> bool) -> Vec<IVec2> {
+    if !passable(start) {
+        return Vec::new();
+    }
+    let mut region = Vec::new();
+    let mut frontier = vec![start];
+    let mut visited = HashSet::from([start]);
+
+    while let Some(pos) = frontier.pop() {
+        region.push(pos);
+        for offset in NEIGHBOURS {
+            let next = pos + offset;
+            if !visited.contains(&next) && passable(next) {
+                visited.insert(next);
+                frontier.push(next);
+            }
+        }
+    }
+
+    region
+}
+
+/// The separate walkable areas of a grid, largest first.
+pub fn regions(grid: &TileGrid) -> Vec<Vec<IVec2>> {
+    let walkable = |pos: IVec2| grid.get(pos).is_some_and(|item| !item.solid);
+    let mut seen = HashSet::new();
+    let mut regions = Vec::new();
+
+    for pos in grid.positions() {
+        if seen.contains(&pos) || !walkable(pos) {
+            continue;
+        }
+        let region = flood_fill(pos, walkable);
+        seen.extend(region.iter().copied());
+        regions.push(region);
+    }
+
+    // Stable sort keeps equal-sized regions in scan order, so the result is
+    // reproducible for a given grid
+    regions.sort_by_key(|region| std::cmp::Reverse(region.len()));
+    regions
+}
+
+/// Make every walkable tile of `grid` reachable from every other.
+///
+/// `anchors` are forced open and always kept, e.g. the tiles where a chunk
+/// meets its neighbours. Other pockets too small to matter are filled with
+/// the solid terrain around them, and the rest are joined to the largest
+/// area by carving an L-shaped path of `path` tiles through solid terrain.
+pub fn connect_regions(grid: &mut TileGrid, anchors: &[IVec2], mut path: impl FnMut() -> MapItem) {
+    for &anchor in anchors {
+        if grid.get(anchor).is_some_and(|item| item.solid) {
+            grid.set(anchor, path());
+        }
+    }
+
+    let mut regions = regions(grid);
+    if regions.len() < 2 {
+        return;
+    }
+
+    // Fill in tiny pockets that nothing needs to reach
+    let fill = grid
+        .positions()
+        .find_map(|pos| grid.get(pos).filter(|item| item.solid).cloned());
+    if let Some(fill) = fill {
+        regions.retain(|region| {
+            let keep =
+                region.len() >= MIN_REGION_SIZE || region.iter().any(|pos| anchors.contains(pos));
+            if !keep {
+                for &pos in region {
+                    grid.set(pos, fill.clone());
+                }
+            }
+            keep
+        });
+    }
+
+    // Join each remaining region to the closest tile already connected
+    let mut connected: Vec<IVec2> = regions.first().cloned().unwrap_or_default();
+    for region in regions.iter().skip(1) {
+        let Some((from, to)) = closest_pair(region, &connected) else {
+            continue;
+        };
+        let corner = IVec2::new(to.x, from.y);
+        for pos in line(from, corner).chain(line(corner, to)) {
+            if grid.get(pos).is_some_and(|item| item.solid) {
+                grid.set(pos, path());
+                connected.push(pos);
+            }
+        }
+        connected.extend(region.iter().copied());
+    }
+}
+
+/// The pair of positions, one from each set, with the shortest Manhattan
+/// distance between them.
+fn closest_pair(from: &[IVec2], to: &[IVec2]) -> Option<(IVec2, IVec2)> {
+    from.iter()
+        .flat_map(|&a| to.iter().map(move |&b| (a, b, (a - b).abs().element_sum())))
+        .min_by_key(|&(a, b, distance)| (distance, a.y, a.x, b.y, b.x))
+        .map(|(a, b, _)| (a, b))
+}
+
+/// Tiles along an axis-aligned line, inclusive of both ends.
+pub(super) fn line(from: IVec2, to: IVec2) -> impl Iterator<Item = IVec2> {
+    let step = (to - from).signum();
+    let length = (to - from).abs().max_element();
+    (0..=length).map(move |i| from + step * i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::biome::Biome;
+    use crate::map::terrain::TerrainCatalog;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    /// A grid drawn with `#` for rock and `.` for grass.
+    fn grid(rows: &[&str]) -> TileGrid {
+        let terrain = TerrainCatalog::base();
+        let mut rng = StdRng::seed_from_u64(0);
+        let size = IVec2::new(rows[0].len() as i32, rows.len() as i32);
+        TileGrid::from_fn(size, |pos| {
+            let id = match rows[pos.y as usize].as_bytes()[pos.x as usize] {
+                b'#' => "rock",
+                _ => "grass",
+            };
+            (terrain.item(id, &mut rng), Biome::Forest)
+        })
+    }
+
+    fn walkable(grid: &TileGrid, pos: IVec2) -> bool {
+        grid.get(pos).is_some_and(|item| !item.solid)
+    }
+
+    const ROOMS: [&str; 5] = [
+        "#########",
+        "#..#...##",
+        "#..#...##",
+        "####...#.",
+        "#########",
+    ];
+
+    #[test]
+    fn flood_fill_stays_inside_a_region() {
+        let grid = grid(&ROOMS);
+        let mut region = flood_fill(IVec2::new(1, 1), |pos| walkable(&grid, pos));
+        region.sort_by_key(|pos| (pos.y, pos.x));
+        assert_eq!(
+            region,
+            [
+                IVec2::new(1, 1),
+                IVec2::new(2, 1),
+                IVec2::new(1, 2),
+                IVec2::new(2, 2)
+            ]
+        );
+        assert!(flood_fill(IVec2::ZERO, |pos| walkable(&grid, pos)).is_empty());
+    }
+
+    #[test]
+    fn regions_are_largest_first() {
+        let sizes: Vec<usize> = regions(&grid(&ROOMS)).iter().map(Vec::len).collect();
+        assert_eq!(sizes, [9, 4, 1]);
+    }
+
+    #[test]
+    fn connect_regions_leaves_a_single_region() {
+        let mut grid = grid(&ROOMS);
+        let terrain = TerrainCatalog::base();
+        let mut rng = StdRng::seed_from_u64(0);
+        connect_regions(&mut grid, &[], || terrain.item("earth", &mut rng));
+
+        let regions = regions(&grid);
+        assert_eq!(regions.len(), 1);
+        // Both rooms survive, the one-tile pocket is filled in
+        assert!(walkable(&grid, IVec2::new(1, 1)) && walkable(&grid, IVec2::new(5, 2)));
+        assert!(!walkable(&grid, IVec2::new(8, 3)));
+    }
+
+    #[test]
+    fn connect_regions_keeps_anchored_pockets() {
+        let mut grid = grid(&ROOMS);
+        let terrain = TerrainCatalog::base();
+        let mut rng = StdRng::seed_from_u64(0);
+        let anchors = [IVec2::new(8, 3), IVec2::new(4, 0)];
+        connect_regions(&mut grid, &anchors, || terrain.item("earth", &mut rng));
+
+        assert_eq!(regions(&grid).len(), 1);
+        assert!(anchors.iter().all(|&anchor| walkable(&grid, anchor)));
+    }
+}
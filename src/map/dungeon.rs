@@ -0,0 +1,217 @@
+use super::biome::Biome;
+use super::connectivity::{connect_regions, line};
+use super::generator::MapGenerator;
+use super::terrain::TerrainCatalog;
+use super::tile_grid::TileGrid;
+use bevy::prelude::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Cell {
+    Wall,
+    Floor,
+    Door,
+    StairsUp,
+    StairsDown,
+}
+
+/// Cave and ruin levels made of rectangular rooms joined by corridors, with
+/// stairs up in the first room and stairs down in the last.
+pub struct DungeonGenerator {
+    /// Level size in tiles when none is asked for.
+    pub size: IVec2,
+    pub room_attempts: usize,
+    pub min_room_size: i32,
+    pub max_room_size: i32,
+}
+
+impl Default for DungeonGenerator {
+    fn default() -> Self {
+        Self {
+            size: IVec2::new(64, 48),
+            room_attempts: 30,
+            min_room_size: 4,
+            max_room_size: 10,
+        }
+    }
+}
+
+impl DungeonGenerator {
+    /// A random room length, no longer than `largest`.
+    fn room_side(&self, largest: i32, rng: &mut impl Rng) -> i32 {
+        let max = self.max_room_size.min(largest);
+        rng.gen_range(self.min_room_size.min(max)..=max)
+    }
+
+    /// Place non-overlapping rooms, keeping a wall between neighbours and
+    /// around the edge of the level. Rooms shrink to fit small levels, and a
+    /// level too small for any room is left solid.
+    fn place_rooms(&self, size: IVec2, rng: &mut impl Rng) -> Vec<IRect> {
+        let mut rooms: Vec<IRect> = Vec::new();
+
+        // A wall on either side, and at least one spot to put the room
+        let largest = size - IVec2::splat(3);
+        if largest.min_element() < 1 {
+            return rooms;
+        }
+
+        for _ in 0..self.room_attempts {
+            let room_size = IVec2::new(
+                self.room_side(largest.x, rng),
+                self.room_side(largest.y, rng),
+            );
+            let min = IVec2::new(
+                rng.gen_range(1..size.x - room_size.x - 1),
+                rng.gen_range(1..size.y - room_size.y - 1),
+            );
+            let room = IRect::from_corners(min, min + room_size - IVec2::ONE);
+
+            let overlaps = rooms
+                .iter()
+                .any(|other| !other.inflate(1).intersect(room).is_empty());
+            if !overlaps {
+                rooms.push(room);
+            }
+        }
+
+        rooms
+    }
+
+    fn carve(&self, size: IVec2, rng: &mut impl Rng) -> Vec<Cell> {
+        let mut cells = vec![Cell::Wall; (size.x * size.y) as usize];
+        let index = |pos: IVec2| (pos.y * size.x + pos.x) as usize;
+
+        let rooms = self.place_rooms(size, rng);
+        for room in &rooms {
+            for y in room.min.y..=room.max.y {
+                for x in room.min.x..=room.max.x {
+                    cells[index(IVec2::new(x, y))] = Cell::Floor;
+                }
+            }
+        }
+
+        // Join each room to the next with an L-shaped corridor, putting a
+        // door wherever a corridor steps through a room's wall
+        let is_doorway = |pos: IVec2, step: IVec2| {
+            rooms
+                .iter()
+                .any(|room| room.contains(pos + step) || room.contains(pos - step))
+        };
+        for pair in rooms.windows(2) {
+            let (from, to) = (pair[0].center(), pair[1].center());
+            let corner = if rng.gen_bool(0.5) {
+                IVec2::new(to.x, from.y)
+            } else {
+                IVec2::new(from.x, to.y)
+            };
+            for (start, end) in [(from, corner), (corner, to)] {
+                let step = (end - start).signum();
+                for pos in line(start, end) {
+                    let cell = &mut cells[index(pos)];
+                    if *cell == Cell::Wall {
+                        *cell = if is_doorway(pos, step) {
+                            Cell::Door
+                        } else {
+                            Cell::Floor
+                        };
+                    }
+                }
+            }
+        }
+
+        if let (Some(first), Some(last)) = (rooms.first(), rooms.last()) {
+            cells[index(first.center())] = Cell::StairsUp;
+            if rooms.len() > 1 {
+                cells[index(last.center())] = Cell::StairsDown;
+            }
+        }
+
+        cells
+    }
+}
+
+impl MapGenerator for DungeonGenerator {
+    fn name(&self) -> &str {
+        "dungeon"
+    }
+
+    fn default_size(&self) -> IVec2 {
+        self.size
+    }
+
+    fn generate(&self, terrain: &TerrainCatalog, seed: u64, size: IVec2) -> TileGrid {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let cells = self.carve(size, &mut rng);
+
+        let mut grid = TileGrid::from_fn(size, |pos| {
+            let terrain_id = match cells[(pos.y * size.x + pos.x) as usize] {
+                Cell::Wall => "dungeon_wall",
+                Cell::Floor => "dungeon_floor",
+                Cell::Door => "door",
+                Cell::StairsUp => "stairs_up",
+                Cell::StairsDown => "stairs_down",
+            };
+            (terrain.item(terrain_id, &mut rng), Biome::Ruins)
+        });
+
+        // Corridors already join consecutive rooms, but a room squeezed
+        // between others can still end up cut off
+        let floor = terrain.get("dungeon_floor");
+        connect_regions(&mut grid, &[], || floor.to_map_item(&mut rng));
+        grid
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::connectivity::regions;
+
+    fn count(grid: &TileGrid, terrain_id: &str) -> usize {
+        grid.positions()
+            .filter(|&pos| grid.get(pos).unwrap().terrain == terrain_id)
+            .count()
+    }
+
+    #[test]
+    fn levels_are_connected_with_stairs_both_ways() {
+        let terrain = TerrainCatalog::base();
+        let generator = DungeonGenerator::default();
+        for seed in 0..8 {
+            let grid = generator.generate(&terrain, seed, generator.size);
+            assert_eq!(grid.size, generator.size);
+            assert_eq!(regions(&grid).len(), 1, "seed {seed}");
+            assert_eq!(count(&grid, "stairs_up"), 1, "seed {seed}");
+            assert_eq!(count(&grid, "stairs_down"), 1, "seed {seed}");
+        }
+    }
+
+    #[test]
+    fn same_seed_same_level() {
+        let terrain = TerrainCatalog::base();
+        let generator = DungeonGenerator::default();
+        let terrain_ids = |seed| {
+            let grid = generator.generate(&terrain, seed, generator.size);
+            grid.positions()
+                .map(|pos| grid.get(pos).unwrap().terrain.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(terrain_ids(7), terrain_ids(7));
+        assert_ne!(terrain_ids(7), terrain_ids(8));
+    }
+
+    #[test]
+    fn small_levels_do_not_panic() {
+        let terrain = TerrainCatalog::base();
+        let generator = DungeonGenerator::default();
+        for x in 1..12 {
+            for y in 1..12 {
+                let size = IVec2::new(x, y);
+                let grid = generator.generate(&terrain, 1, size);
+                assert_eq!(grid.size, size);
+            }
+        }
+        let solid = generator.generate(&terrain, 1, IVec2::new(3, 3));
+        assert_eq!(count(&solid, "dungeon_wall"), 9);
+    }
+}
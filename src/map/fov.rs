@@ -0,0 +1,143 @@
+use bevy::{prelude::*, utils::HashSet};
+
+/// Tiles at least this opaque cast shadows. Lighter ones, like marsh mist or
+/// the stalks of a rice paddy, don't block sight.
+pub const BLOCKS_SIGHT: f32 = 0.5;
+
+/// How each octant's `(column, row)` maps onto the grid, as
+/// `[xx, xy, yx, yy]`.
+const OCTANTS: [[i32; 4]; 8] = [
+    [1, 0, 0, 1],
+    [0, 1, 1, 0],
+    [0, -1, 1, 0],
+    [-1, 0, 0, 1],
+    [-1, 0, 0, -1],
+    [0, -1, -1, 0],
+    [0, 1, -1, 0],
+    [1, 0, 0, -1],
+];
+
+/// Every tile visible from `origin` within `radius`, by recursive
+/// shadowcasting. Opaque tiles are themselves visible, so walls show up, but
+/// hide whatever is behind them.
+pub fn field_of_view(origin: IVec2, radius: i32, opacity: impl Fn(IVec2) -> f32) -> HashSet<IVec2> {
+    let blocks = |pos: IVec2| opacity(pos) >= BLOCKS_SIGHT;
+    let mut caster = Shadowcaster {
+        origin,
+        radius,
+        blocks: &blocks,
+        visible: HashSet::from([origin]),
+    };
+    for octant in OCTANTS {
+        caster.cast(1, 1.0, 0.0, octant);
+    }
+    caster.visible
+}
+
+struct Shadowcaster<'a, F: Fn(IVec2) -> bool> {
+    origin: IVec2,
+    radius: i32,
+    blocks: &'a F,
+    visible: HashSet<IVec2>,
+}
+
+impl<F: Fn(IVec2) -> bool> Shadowcaster<'_, F> {
+    /// Scan one octant from `row` outwards, between the slopes `start` and
+    /// `end`, recursing past each obstacle to scan the light around it.
+    fn cast(&mut self, row: i32, mut start: f32, end: f32, octant: [i32; 4]) {
+        if start < end {
+            return;
+        }
+        let [xx, xy, yx, yy] = octant;
+        let mut next_start = start;
+
+        for distance in row..=self.radius {
+            let mut blocked = false;
+            let dy = -distance;
+            for dx in -distance..=0 {
+                let left_slope = (dx as f32 - 0.5) / (dy as f32 + 0.5);
+                let right_slope = (dx as f32 + 0.5) / (dy as f32 - 0.5);
+                if start < right_slope {
+                    continue;
+                }
+                if end > left_slope {
+                    break;
+                }
+
+                let pos = self.origin + IVec2::new(dx * xx + dy * xy, dx * yx + dy * yy);
+                if dx * dx + dy * dy <= self.radius * self.radius {
+                    self.visible.insert(pos);
+                }
+
+                if blocked {
+                    if (self.blocks)(pos) {
+                        next_start = right_slope;
+                    } else {
+                        blocked = false;
+                        start = next_start;
+                    }
+                } else if (self.blocks)(pos) && distance < self.radius {
+                    blocked = true;
+                    self.cast(distance + 1, start, left_slope, octant);
+                    next_start = right_slope;
+                }
+            }
+            if blocked {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn open_ground_is_seen_out_to_the_radius() {
+        let visible = field_of_view(IVec2::ZERO, 4, |_| 0.0);
+        assert!(visible.contains(&IVec2::ZERO));
+        for pos in [IVec2::new(4, 0), IVec2::new(0, -4), IVec2::new(2, 3)] {
+            assert!(visible.contains(&pos), "{pos}");
+        }
+        assert!(!visible.contains(&IVec2::new(5, 0)));
+        assert!(!visible.contains(&IVec2::new(3, 3)));
+        // Symmetric in every octant
+        assert!(visible.iter().all(|pos| visible.contains(&-*pos)));
+    }
+
+    #[test]
+    fn walls_are_seen_but_hide_what_is_behind() {
+        let wall = IVec2::new(2, 0);
+        let visible = field_of_view(IVec2::ZERO, 8, |pos| if pos == wall { 1.0 } else { 0.0 });
+        assert!(visible.contains(&wall));
+        assert!(!visible.contains(&IVec2::new(3, 0)));
+        assert!(!visible.contains(&IVec2::new(6, 0)));
+        assert!(visible.contains(&IVec2::new(2, 1)));
+    }
+
+    #[test]
+    fn light_cover_does_not_block_sight() {
+        let visible = field_of_view(IVec2::ZERO, 6, |pos| {
+            if pos.x == 2 {
+                BLOCKS_SIGHT - 0.1
+            } else {
+                0.0
+            }
+        });
+        assert!(visible.contains(&IVec2::new(5, 0)));
+
+        let enclosed = field_of_view(
+            IVec2::ZERO,
+            6,
+            |pos| {
+                if pos == IVec2::ZERO {
+                    0.0
+                } else {
+                    1.0
+                }
+            },
+        );
+        assert_eq!(enclosed.len(), 9);
+    }
+}
@@ -1,76 +1,192 @@
-use super::terrain::*;
+use super::biome::Biome;
+use super::chunk::{chunk_origin, chunk_seed, CHUNK_SIZE};
+use super::connectivity::connect_regions;
+use super::hydrology::water_in_chunk;
+use super::map_file::MapFile;
+use super::terrain::TerrainCatalog;
+use super::tile_grid::TileGrid;
 use bevy::prelude::*;
 use noise::{NoiseFn, Perlin};
-use rand::Rng;
-use crate::TerrainEntity;
-use crate::create_text_color_bundle;
-
-pub fn generate_terrain(
-    commands: &mut Commands,
-    font: Handle<Font>,
-    width: f32,
-    height: f32,
-    char_size: f32,
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+/// "Zoom level" of the per-tile detail noise that varies terrain in a biome.
+const DETAIL_SCALE: f64 = 0.1;
+/// Elevation changes slowly so mountains and lowlands span many chunks.
+const ELEVATION_SCALE: f64 = 0.02;
+const MOISTURE_SCALE: f64 = 0.03;
+/// Chance that a chunk has a vault stamped into it, if one fits.
+const VAULT_CHANCE: f64 = 0.1;
+
+/// The three noise layers the overworld is built from.
+struct OverworldNoise {
+    detail: Perlin,
+    elevation: Perlin,
+    moisture: Perlin,
+}
+
+impl OverworldNoise {
+    fn new(seed: u64) -> Self {
+        let noise_seed = (seed ^ (seed >> 32)) as u32;
+        Self {
+            detail: Perlin::new(noise_seed),
+            elevation: Perlin::new(noise_seed.wrapping_add(1)),
+            moisture: Perlin::new(noise_seed.wrapping_add(2)),
+        }
+    }
+
+    /// Sample a layer at a tile position, normalized from [-1, 1] to [0, 1].
+    fn sample(perlin: &Perlin, position: IVec2, scale: f64) -> f64 {
+        let value = perlin.get([position.x as f64 * scale, position.y as f64 * scale]);
+        ((value + 1.0) / 2.0).clamp(0.0, 1.0)
+    }
+}
+
+/// Generate the overworld terrain for one chunk, with grid position `(0, 0)`
+/// at the chunk's top-left tile. Noise is sampled in world tile coordinates
+/// so chunks line up seamlessly, and variant selection uses a per-chunk RNG
+/// so the result only depends on `seed`, `chunk`, the terrain catalog and the
+/// available `vaults`.
+pub fn generate_chunk(
+    terrain: &TerrainCatalog,
+    seed: u64,
+    chunk: IVec2,
+    vaults: &[MapFile],
+) -> TileGrid {
+    let mut rng = StdRng::seed_from_u64(chunk_seed(seed, chunk));
+    let noise = OverworldNoise::new(seed);
+    let water = water_in_chunk(seed, chunk, |position| {
+        OverworldNoise::sample(&noise.elevation, position, ELEVATION_SCALE)
+    });
+
+    let origin = chunk_origin(chunk);
+
+    let mut grid = TileGrid::from_fn(IVec2::splat(CHUNK_SIZE), |local| {
+        let position = origin + local;
+        let detail = OverworldNoise::sample(&noise.detail, position, DETAIL_SCALE);
+        let elevation = OverworldNoise::sample(&noise.elevation, position, ELEVATION_SCALE);
+        let moisture = OverworldNoise::sample(&noise.moisture, position, MOISTURE_SCALE);
+
+        // Elevation and moisture pick the biome, detail varies within it,
+        // and rivers and lakes cut through whatever biome they cross
+        let biome = water
+            .get(&position)
+            .copied()
+            .unwrap_or_else(|| Biome::classify(elevation, moisture));
+        let terrain_id = biome.terrain(detail, elevation);
+
+        // Convert terrain to map item
+        (terrain.item(terrain_id, &mut rng), biome)
+    });
+
+    if rng.gen_bool(VAULT_CHANCE) {
+        place_vault(&mut grid, terrain, vaults, &mut rng);
+    }
+
+    // Open a gate midway along each edge and connect everything in the chunk
+    // to them. Neighbouring chunks' gates line up, so every walkable tile in
+    // the overworld is reachable from every other
+    let path = terrain.get("earth");
+    connect_regions(&mut grid, &chunk_gates(), || path.to_map_item(&mut rng));
+
+    // The overworld's entrance to the dungeons sits in the origin chunk
+    if chunk == IVec2::ZERO {
+        place_dungeon_entrance(&mut grid, terrain, &mut rng);
+    }
+
+    grid
+}
+
+/// Stamp a vault that fits in the chunk and suits the biome at the spot
+/// picked for it. Vaults go in before connecting the chunk so their interiors
+/// are reachable.
+fn place_vault(
+    grid: &mut TileGrid,
+    terrain: &TerrainCatalog,
+    vaults: &[MapFile],
+    rng: &mut StdRng,
 ) {
-    let spacing = char_size;
-
-    // Calculate visible grid dimensions
-    let cols = (width / spacing) as i32;
-    let rows = (height / spacing) as i32;
-
-    // Calculate starting position (top-left corner)
-    let start_x = -width / 2.0 + spacing / 2.0;
-    let start_y = height / 2.0 - spacing / 2.0;
-
-    let mut rng = rand::thread_rng();
-
-    let perlin = Perlin::new(rng.gen());
-    let scale = 0.1; // Adjust this to change the "zoom level" of the noise
-
-    // Create terrain grid
-    for row in 0..rows {
-        for col in 0..cols {
-            let x = start_x + col as f32 * spacing;
-            let y = start_y - row as f32 * spacing;
-
-            // Generate noise value for this position
-            let noise_val = perlin.get([col as f64 * scale, row as f64 * scale]);
-            // Normalize noise from [-1, 1] to [0, 1]
-            let normalized_noise = (noise_val + 1.0) / 2.0;
-
-            // Use noise value to determine terrain type
-            let terrain = match normalized_noise {
-                n if n < 0.3 => earth(),    // Clearings/paths (30%)
-                n if n < 0.6 => grass(),    // Forest floor (30%)
-                n if n < 0.9 => tree(),     // Dense forest (30%)
-                _ => rock(),                // Rocky outcrops (10%)
-            };
-
-            // Add some randomness for variation
-            if rng.gen_range(0.0..1.0) < 0.1 {  // 10% chance to override
-                let _terrain = match rng.gen_range(0..100) {
-                    0..=50 => grass(),
-                    51..=80 => tree(),
-                    _ => rock(),
-                };
-            }
+    let fitting: Vec<&MapFile> = vaults
+        .iter()
+        .filter(|vault| vault.size.cmple(grid.size).all())
+        .collect();
+    let Ok(vault) = fitting.choose_weighted(rng, |vault| vault.weight()) else {
+        return;
+    };
+
+    let slack = grid.size - vault.size;
+    let origin = IVec2::new(rng.gen_range(0..=slack.x), rng.gen_range(0..=slack.y));
+    let suits = grid
+        .biome(origin + vault.size / 2)
+        .is_some_and(|biome| vault.suits_biome(biome));
+    if suits {
+        vault.stamp(grid, terrain, origin, rng);
+    }
+}
 
-            // Convert terrain to map item
-            let map_item = terrain.to_map_item();
-
-            // Spawn the terrain entity
-            commands.spawn((
-                create_text_color_bundle(
-                    font.clone(),
-                    map_item.current_character(),
-                    x,
-                    y,
-                    0.0,
-                    map_item.current_color(),
-                ),
-                map_item,
-                TerrainEntity,
-            ));
+/// Local positions where a chunk is always open to its neighbours.
+fn chunk_gates() -> [IVec2; 4] {
+    let (middle, last) = (CHUNK_SIZE / 2, CHUNK_SIZE - 1);
+    [
+        IVec2::new(middle, 0),
+        IVec2::new(middle, last),
+        IVec2::new(0, middle),
+        IVec2::new(last, middle),
+    ]
+}
+
+/// Replace a random dry, walkable tile with stairs down into the dungeons.
+fn place_dungeon_entrance(grid: &mut TileGrid, terrain: &TerrainCatalog, rng: &mut StdRng) {
+    let candidates: Vec<IVec2> = grid
+        .positions()
+        .filter(|&pos| grid.get(pos).is_some_and(|item| !item.solid && !item.water))
+        .collect();
+    let position = candidates.choose(rng).copied().unwrap_or(grid.size / 2);
+    grid.set(position, terrain.item("stairs_down", rng));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    /// Everything about a grid that depends on the seed.
+    fn snapshot(grid: &TileGrid) -> Vec<(String, usize, Biome)> {
+        grid.positions()
+            .map(|pos| {
+                let item = grid.get(pos).unwrap();
+                (
+                    item.terrain.clone(),
+                    item.current_variant,
+                    grid.biome(pos).unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn chunks_only_depend_on_seed_and_coordinate() {
+        let terrain = TerrainCatalog::base();
+        let vaults = MapFile::load_dir(Path::new("assets/vaults"));
+        let chunk = IVec2::new(3, -2);
+
+        let first = generate_chunk(&terrain, 42, chunk, &vaults);
+        let second = generate_chunk(&terrain, 42, chunk, &vaults);
+        assert_eq!(first.size, IVec2::splat(CHUNK_SIZE));
+        assert_eq!(snapshot(&first), snapshot(&second));
+        assert_eq!(first.placements.len(), second.placements.len());
+
+        let other = generate_chunk(&terrain, 43, chunk, &vaults);
+        assert_ne!(snapshot(&first), snapshot(&other));
+    }
+
+    #[test]
+    fn chunk_gates_stay_open() {
+        let terrain = TerrainCatalog::base();
+        for seed in 0..8 {
+            let grid = generate_chunk(&terrain, seed, IVec2::ZERO, &[]);
+            for gate in chunk_gates() {
+                assert!(!grid.get(gate).unwrap().solid, "seed {seed} gate {gate}");
+            }
         }
     }
 }
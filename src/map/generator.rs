@@ -0,0 +1,237 @@
+use super::biome::Biome;
+use super::chunk::{chunk_of, chunk_origin, chunk_seed};
+use super::dungeon::DungeonGenerator;
+use super::generation::generate_chunk;
+use super::map_file::MapFile;
+use super::terrain::TerrainCatalog;
+use super::tile_grid::TileGrid;
+use bevy::{prelude::*, utils::HashMap};
+use rand::{rngs::StdRng, SeedableRng};
+use std::path::Path;
+
+/// Decides the terrain of a level. Generators only produce data; spawning
+/// entities for it is left to the chunk streaming systems, so generators can
+/// be run and inspected without an `App`.
+pub trait MapGenerator: Send + Sync {
+    /// Name the generator is registered and selected by.
+    fn name(&self) -> &str;
+
+    /// Size of the level when this generator is used for one.
+    fn default_size(&self) -> IVec2;
+
+    fn generate(&self, terrain: &TerrainCatalog, seed: u64, size: IVec2) -> TileGrid;
+
+    /// Generators for endless maps produce any chunk on demand; finite
+    /// generators return `None` and are generated whole by `generate`.
+    fn generate_chunk(
+        &self,
+        _terrain: &TerrainCatalog,
+        _seed: u64,
+        _chunk: IVec2,
+    ) -> Option<TileGrid> {
+        None
+    }
+}
+
+/// The endless Perlin-noise overworld, streamed in chunk by chunk, with
+/// hand-authored vaults stamped into some chunks.
+#[derive(Default)]
+pub struct OverworldGenerator {
+    pub vaults: Vec<MapFile>,
+}
+
+impl MapGenerator for OverworldGenerator {
+    fn name(&self) -> &str {
+        "overworld"
+    }
+
+    fn default_size(&self) -> IVec2 {
+        IVec2::new(160, 100)
+    }
+
+    fn generate(&self, terrain: &TerrainCatalog, seed: u64, size: IVec2) -> TileGrid {
+        let mut chunks = HashMap::default();
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut grid = TileGrid::from_fn(size, |pos| {
+            let chunk = chunk_of(pos);
+            let grid = chunks
+                .entry(chunk)
+                .or_insert_with(|| generate_chunk(terrain, seed, chunk, &self.vaults));
+            let local = pos - chunk_origin(chunk);
+            match (grid.get(local), grid.biome(local)) {
+                (Some(item), Some(biome)) => (item.clone(), biome),
+                _ => (terrain.item("void", &mut rng), Biome::Forest),
+            }
+        });
+
+        for (chunk, chunk_grid) in chunks {
+            for (local, placement) in chunk_grid.placements {
+                let pos = chunk_origin(chunk) + local;
+                if grid.contains(pos) {
+                    grid.place(pos, placement);
+                }
+            }
+        }
+        grid
+    }
+
+    fn generate_chunk(
+        &self,
+        terrain: &TerrainCatalog,
+        seed: u64,
+        chunk: IVec2,
+    ) -> Option<TileGrid> {
+        Some(generate_chunk(terrain, seed, chunk, &self.vaults))
+    }
+}
+
+/// A level drawn by hand in a map file instead of generated.
+pub struct FileMapGenerator {
+    pub map: MapFile,
+}
+
+impl MapGenerator for FileMapGenerator {
+    fn name(&self) -> &str {
+        &self.map.name
+    }
+
+    fn default_size(&self) -> IVec2 {
+        self.map.size
+    }
+
+    /// The file's map, whatever size is asked for; only tile variants
+    /// depend on the seed.
+    fn generate(&self, terrain: &TerrainCatalog, seed: u64, _size: IVec2) -> TileGrid {
+        self.map.to_grid(terrain, &mut StdRng::seed_from_u64(seed))
+    }
+}
+
+/// Every generator available to the game, looked up by name. Each depth uses
+/// the overworld at 0 and dungeons below unless overridden, e.g. by the
+/// debug command that cycles generators.
+#[derive(Resource)]
+pub struct MapGeneratorRegistry {
+    generators: Vec<Box<dyn MapGenerator>>,
+    level_overrides: HashMap<u32, String>,
+}
+
+impl Default for MapGeneratorRegistry {
+    fn default() -> Self {
+        let mut registry = Self {
+            generators: Vec::new(),
+            level_overrides: HashMap::default(),
+        };
+        registry.register(OverworldGenerator::default());
+        registry.register(DungeonGenerator::default());
+        registry
+    }
+}
+
+impl MapGeneratorRegistry {
+    /// The built-in generators plus whatever map files are in `dir`: vaults
+    /// for the overworld from `vaults/`, and whole levels from `maps/`, each
+    /// registered under its map's name.
+    pub fn from_assets(dir: &Path) -> Self {
+        let mut registry = Self::default();
+        registry.register(OverworldGenerator {
+            vaults: MapFile::load_dir(&dir.join("vaults")),
+        });
+        for map in MapFile::load_dir(&dir.join("maps")) {
+            registry.register(FileMapGenerator { map });
+        }
+        registry
+    }
+
+    /// Add a generator, replacing any already registered under its name.
+    pub fn register(&mut self, generator: impl MapGenerator + 'static) {
+        let existing = self
+            .generators
+            .iter()
+            .position(|existing| existing.name() == generator.name());
+        match existing {
+            Some(index) => self.generators[index] = Box::new(generator),
+            None => self.generators.push(Box::new(generator)),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn MapGenerator> {
+        self.generators
+            .iter()
+            .find(|generator| generator.name() == name)
+            .map(|generator| generator.as_ref())
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.generators.iter().map(|generator| generator.name())
+    }
+
+    pub fn generator_for_depth(&self, depth: u32) -> &dyn MapGenerator {
+        let name = self.level_overrides.get(&depth).map_or(
+            if depth == 0 { "overworld" } else { "dungeon" },
+            String::as_str,
+        );
+        self.get(name)
+            .or_else(|| self.generators.first().map(|generator| generator.as_ref()))
+            .expect("at least one map generator is registered")
+    }
+
+    pub fn set_generator_for_depth(&mut self, depth: u32, name: &str) {
+        self.level_overrides.insert(depth, name.to_string());
+    }
+}
+
+/// Seed for a level, so each depth differs but stays reproducible.
+pub fn level_seed(world_seed: u64, depth: u32) -> u64 {
+    match depth {
+        0 => world_seed,
+        _ => chunk_seed(world_seed, IVec2::new(depth as i32, -1)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::chunk::CHUNK_SIZE;
+
+    #[test]
+    fn overworld_matches_its_streamed_chunks() {
+        let terrain = TerrainCatalog::base();
+        let generator = OverworldGenerator::default();
+        let size = IVec2::splat(CHUNK_SIZE * 2);
+        let grid = generator.generate(&terrain, 5, size);
+        assert_eq!(grid.size, size);
+
+        for chunk in [IVec2::new(0, 0), IVec2::new(1, 1)] {
+            let streamed = generator.generate_chunk(&terrain, 5, chunk).unwrap();
+            for local in streamed.positions() {
+                let pos = chunk_origin(chunk) + local;
+                let (whole, part) = (grid.get(pos).unwrap(), streamed.get(local).unwrap());
+                assert_eq!(whole.terrain, part.terrain, "at {pos}");
+                assert_eq!(whole.current_variant, part.current_variant, "at {pos}");
+            }
+        }
+    }
+
+    #[test]
+    fn registry_picks_generators_by_depth() {
+        let mut registry = MapGeneratorRegistry::default();
+        assert_eq!(registry.generator_for_depth(0).name(), "overworld");
+        assert_eq!(registry.generator_for_depth(3).name(), "dungeon");
+
+        registry.set_generator_for_depth(0, "dungeon");
+        registry.set_generator_for_depth(1, "missing");
+        assert_eq!(registry.generator_for_depth(0).name(), "dungeon");
+        assert_eq!(registry.generator_for_depth(1).name(), "overworld");
+        assert!(registry
+            .generator_for_depth(1)
+            .generate_chunk(&TerrainCatalog::base(), 0, IVec2::ZERO)
+            .is_some());
+    }
+
+    #[test]
+    fn levels_get_their_own_seeds() {
+        assert_eq!(level_seed(9, 0), 9);
+        assert_ne!(level_seed(9, 1), level_seed(9, 2));
+        assert_eq!(level_seed(9, 1), level_seed(9, 1));
+    }
+}
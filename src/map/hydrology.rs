@@ -0,0 +1,110 @@
+use super::biome::Biome;
+use super::chunk::{chunk_origin, chunk_seed, CHUNK_SIZE};
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+/// Springs are scattered per region of this many tiles square.
+const REGION_SIZE: i32 = 64;
+const SPRINGS_PER_REGION: usize = 6;
+/// Only high ground feeds a river.
+const SPRING_ELEVATION: f64 = 0.6;
+const MAX_RIVER_LENGTH: i32 = 48;
+/// Lakes spread this far (Chebyshev distance) from the basin they fill.
+const LAKE_RADIUS: i32 = 6;
+/// Water rises this far above the basin floor before the lake stops growing.
+const LAKE_DEPTH: f64 = 0.015;
+/// Mixed into the world seed so springs don't correlate with other RNG use.
+const HYDROLOGY_SALT: u64 = 0x4879_6472_6F6C_6F67;
+
+const NEIGHBOURS: [IVec2; 4] = [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y];
+
+// A river and the lake at its end never reach further than this from their
+// spring, so a chunk only has to trace springs in neighbouring regions.
+const _: () = assert!(MAX_RIVER_LENGTH + LAKE_RADIUS <= REGION_SIZE);
+
+/// Trace every river and lake that can reach `chunk`, returning the water
+/// biome for each of the chunk's tiles that ends up underwater.
+///
+/// Rivers run downhill from springs on high ground and pool into a lake when
+/// they reach a basin with no lower neighbour.
+pub fn water_in_chunk(
+    seed: u64,
+    chunk: IVec2,
+    elevation: impl Fn(IVec2) -> f64,
+) -> HashMap<IVec2, Biome> {
+    let min = chunk_origin(chunk);
+    let max = min + IVec2::splat(CHUNK_SIZE - 1);
+    let region = IVec2::new(min.x.div_euclid(REGION_SIZE), min.y.div_euclid(REGION_SIZE));
+
+    let mut water = HashMap::default();
+    for dy in -1..=1 {
+        for dx in -1..=1 {
+            for spring in springs(seed, region + IVec2::new(dx, dy), &elevation) {
+                trace_river(spring, &elevation, &mut water);
+            }
+        }
+    }
+
+    water.retain(|pos, _| pos.cmpge(min).all() && pos.cmple(max).all());
+    water
+}
+
+fn springs(seed: u64, region: IVec2, elevation: &impl Fn(IVec2) -> f64) -> Vec<IVec2> {
+    let mut rng = StdRng::seed_from_u64(chunk_seed(seed ^ HYDROLOGY_SALT, region));
+    let origin = region * REGION_SIZE;
+    (0..SPRINGS_PER_REGION)
+        .map(|_| origin + IVec2::new(rng.gen_range(0..REGION_SIZE), rng.gen_range(0..REGION_SIZE)))
+        .filter(|&pos| elevation(pos) > SPRING_ELEVATION)
+        .collect()
+}
+
+/// Follow the steepest descent from `spring`, marking river tiles, and fill a
+/// lake where the path bottoms out.
+fn trace_river(
+    spring: IVec2,
+    elevation: &impl Fn(IVec2) -> f64,
+    water: &mut HashMap<IVec2, Biome>,
+) {
+    let mut current = spring;
+    for _ in 0..MAX_RIVER_LENGTH {
+        water.entry(current).or_insert(Biome::River);
+
+        let current_elevation = elevation(current);
+        let lowest = NEIGHBOURS
+            .iter()
+            .map(|&offset| current + offset)
+            .map(|pos| (pos, elevation(pos)))
+            .filter(|&(_, height)| height < current_elevation)
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+
+        match lowest {
+            Some((next, _)) => current = next,
+            None => {
+                fill_lake(current, elevation, water);
+                return;
+            }
+        }
+    }
+}
+
+/// Flood outwards from a basin over every connected tile lying less than
+/// `LAKE_DEPTH` above its floor.
+fn fill_lake(basin: IVec2, elevation: &impl Fn(IVec2) -> f64, water: &mut HashMap<IVec2, Biome>) {
+    let surface = elevation(basin) + LAKE_DEPTH;
+    let mut frontier = vec![basin];
+    let mut visited = HashSet::from([basin]);
+
+    while let Some(pos) = frontier.pop() {
+        water.insert(pos, Biome::Pond);
+        for offset in NEIGHBOURS {
+            let next = pos + offset;
+            let within_reach = (next - basin).abs().max_element() <= LAKE_RADIUS;
+            if within_reach && elevation(next) < surface && visited.insert(next) {
+                frontier.push(next);
+            }
+        }
+    }
+}
@@ -0,0 +1,427 @@
+use super::biome::Biome;
+use super::terrain::TerrainCatalog;
+use super::tile_grid::TileGrid;
+use crate::components::LightKind;
+use bevy::{prelude::*, utils::HashMap};
+use rand::Rng;
+use std::{fmt, fs, path::Path};
+
+/// Something other than terrain that a map file puts on a tile.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Placement {
+    /// A monster, by its name in the bestiary (e.g. `Oni`).
+    Monster(String),
+    /// An item lying on the ground, drawn with the glyph from the legend.
+    Item { name: String, glyph: char },
+    /// A lantern, brazier or other fixed light, drawn with the legend glyph.
+    Light { kind: LightKind, glyph: char },
+}
+
+/// What one legend glyph stands for.
+#[derive(Clone)]
+struct LegendEntry {
+    /// Terrain id in the `TerrainCatalog`.
+    terrain: String,
+    placement: Option<Placement>,
+}
+
+/// A hand-authored map or vault read from a plain-text file.
+///
+/// A file has three parts: `key: value` metadata, a `legend:` section with
+/// one glyph per line followed by a terrain id and optional
+/// `monster=<name>`, `item=<name>` (underscores in an item name stand for
+/// spaces) or `light=<kind>`, and a `map:` section drawn with those
+/// glyphs. Spaces in the map are transparent, leaving the terrain underneath
+/// when stamped as a vault. Lines starting with `//` outside the map are
+/// comments. Terrain ids missing from the catalog show up as a magenta `?`.
+///
+/// ```text
+/// name: roadside-shrine
+/// weight: 2
+/// biomes: Forest, BambooGrove
+///
+/// legend:
+/// # rock
+/// . earth
+/// 門 door
+/// O earth monster=Oni
+///
+/// map:
+///  ###
+/// #.O.#
+///  #門#
+/// ```
+#[derive(Clone)]
+pub struct MapFile {
+    pub name: String,
+    pub metadata: HashMap<String, String>,
+    pub size: IVec2,
+    /// Row by row from the top left; `None` is transparent.
+    cells: Vec<Option<LegendEntry>>,
+}
+
+#[derive(Debug)]
+pub enum MapFileError {
+    Io(std::io::Error),
+    /// The file's contents are malformed; `line` counts from 1.
+    Parse {
+        line: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for MapFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MapFileError::Io(err) => write!(f, "could not read map file: {}", err),
+            MapFileError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl std::error::Error for MapFileError {}
+
+impl From<std::io::Error> for MapFileError {
+    fn from(err: std::io::Error) -> Self {
+        MapFileError::Io(err)
+    }
+}
+
+#[derive(PartialEq)]
+enum Section {
+    Header,
+    Legend,
+    Map,
+}
+
+impl MapFile {
+    pub fn parse(text: &str) -> Result<Self, MapFileError> {
+        let mut section = Section::Header;
+        let mut metadata = HashMap::default();
+        let mut legend: HashMap<char, LegendEntry> = HashMap::default();
+        let mut rows: Vec<(usize, Vec<char>)> = Vec::new();
+
+        for (index, raw_line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let error = |message: String| MapFileError::Parse {
+                line: line_number,
+                message,
+            };
+            let line = raw_line.trim();
+
+            if section == Section::Map {
+                rows.push((line_number, raw_line.trim_end().chars().collect()));
+                continue;
+            }
+            if line.is_empty() || line.starts_with("//") {
+                continue;
+            }
+            match line {
+                "legend:" => section = Section::Legend,
+                "map:" => section = Section::Map,
+                _ if section == Section::Header => {
+                    let (key, value) = line
+                        .split_once(':')
+                        .ok_or_else(|| error(format!("expected `key: value`, found `{}`", line)))?;
+                    metadata.insert(key.trim().to_string(), value.trim().to_string());
+                }
+                _ => {
+                    let (glyph, entry) = parse_legend_line(line).map_err(error)?;
+                    legend.insert(glyph, entry);
+                }
+            }
+        }
+
+        // Blank lines around the drawing aren't part of it
+        while rows.last().is_some_and(|(_, row)| row.is_empty()) {
+            rows.pop();
+        }
+        let first_row = rows.iter().position(|(_, row)| !row.is_empty());
+        let rows = &rows[first_row.unwrap_or(rows.len())..];
+        if rows.is_empty() {
+            return Err(MapFileError::Parse {
+                line: text.lines().count(),
+                message: "no `map:` section with at least one row".to_string(),
+            });
+        }
+
+        let width = rows.iter().map(|(_, row)| row.len()).max().unwrap_or(0);
+        let mut cells = Vec::with_capacity(width * rows.len());
+        for (line_number, row) in rows {
+            for x in 0..width {
+                let glyph = row.get(x).copied().unwrap_or(' ');
+                let cell = match (glyph, legend.get(&glyph)) {
+                    (_, Some(entry)) => Some(entry.clone()),
+                    (' ', None) => None,
+                    (_, None) => {
+                        return Err(MapFileError::Parse {
+                            line: *line_number,
+                            message: format!("glyph `{}` is not in the legend", glyph),
+                        })
+                    }
+                };
+                cells.push(cell);
+            }
+        }
+
+        Ok(Self {
+            name: metadata.get("name").cloned().unwrap_or_default(),
+            metadata,
+            size: IVec2::new(width as i32, rows.len() as i32),
+            cells,
+        })
+    }
+
+    /// Read and parse a file, naming the map after the file if its header
+    /// doesn't.
+    pub fn load(path: &Path) -> Result<Self, MapFileError> {
+        let mut map = Self::parse(&fs::read_to_string(path)?)?;
+        if map.name.is_empty() {
+            map.name = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default();
+        }
+        Ok(map)
+    }
+
+    /// Every `.txt` map file in a directory, in file name order. Files that
+    /// fail to load are reported and skipped so one bad file doesn't stop
+    /// the game starting.
+    pub fn load_dir(dir: &Path) -> Vec<Self> {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        let mut paths: Vec<_> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "txt"))
+            .collect();
+        paths.sort();
+
+        paths
+            .into_iter()
+            .filter_map(|path| match Self::load(&path) {
+                Ok(map) => Some(map),
+                Err(err) => {
+                    println!("Skipping map file {}: {}", path.display(), err);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Relative chance of being picked among vaults that fit; defaults to 1.
+    pub fn weight(&self) -> u32 {
+        self.metadata
+            .get("weight")
+            .and_then(|weight| weight.parse().ok())
+            .unwrap_or(1)
+    }
+
+    /// Whether the `biomes` header allows this vault in `biome`. A vault
+    /// without the header fits anywhere.
+    pub fn suits_biome(&self, biome: Biome) -> bool {
+        self.metadata.get("biomes").is_none_or(|biomes| {
+            biomes
+                .split(',')
+                .any(|name| Biome::from_name(name.trim()) == Some(biome))
+        })
+    }
+
+    /// Draw the map over `grid` with its top-left corner at `origin`,
+    /// leaving transparent and out-of-grid tiles alone.
+    pub fn stamp(
+        &self,
+        grid: &mut TileGrid,
+        terrain: &TerrainCatalog,
+        origin: IVec2,
+        rng: &mut impl Rng,
+    ) {
+        for (index, cell) in self.cells.iter().enumerate() {
+            let Some(entry) = cell else {
+                continue;
+            };
+            let local = IVec2::new(index as i32 % self.size.x, index as i32 / self.size.x);
+            let pos = origin + local;
+            if !grid.contains(pos) {
+                continue;
+            }
+            grid.set(pos, terrain.item(&entry.terrain, rng));
+            if let Some(placement) = &entry.placement {
+                grid.place(pos, placement.clone());
+            }
+        }
+    }
+
+    /// A whole level made from this map. Transparent tiles become the
+    /// `fill` terrain from the header (void by default), and every tile
+    /// takes the header's `biome` (Forest by default).
+    pub fn to_grid(&self, terrain: &TerrainCatalog, rng: &mut impl Rng) -> TileGrid {
+        let fill = terrain.get(self.metadata.get("fill").map_or("void", String::as_str));
+        let biome = self
+            .metadata
+            .get("biome")
+            .and_then(|name| Biome::from_name(name))
+            .unwrap_or(Biome::Forest);
+
+        let mut grid = TileGrid::from_fn(self.size, |_| (fill.to_map_item(rng), biome));
+        self.stamp(&mut grid, terrain, IVec2::ZERO, rng);
+        grid
+    }
+}
+
+/// Parse `<glyph> <terrain> [monster=<name>] [item=<name>] [light=<kind>]`.
+fn parse_legend_line(line: &str) -> Result<(char, LegendEntry), String> {
+    let mut chars = line.chars();
+    let glyph = chars.next().ok_or("empty legend line")?;
+    let mut words = chars.as_str().split_whitespace();
+
+    let terrain = words
+        .next()
+        .ok_or_else(|| format!("legend glyph `{}` has no terrain", glyph))?
+        .to_string();
+
+    let mut placement = None;
+    for word in words {
+        placement = Some(match word.split_once('=') {
+            Some(("monster", name)) => Placement::Monster(name.to_string()),
+            Some(("item", name)) => Placement::Item {
+                name: name.replace('_', " "),
+                glyph,
+            },
+            Some(("light", name)) => Placement::Light {
+                kind: LightKind::from_name(name)
+                    .ok_or_else(|| format!("unknown light `{}`", name))?,
+                glyph,
+            },
+            _ => {
+                return Err(format!(
+                    "expected `monster=`, `item=` or `light=`, found `{}`",
+                    word
+                ))
+            }
+        });
+    }
+
+    Ok((glyph, LegendEntry { terrain, placement }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::terrain::TerrainCatalog;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    /// The line and message of a file that should fail to parse.
+    fn parse_error(text: &str) -> (usize, String) {
+        match MapFile::parse(text) {
+            Err(MapFileError::Parse { line, message }) => (line, message),
+            Err(err) => panic!("unexpected error: {err}"),
+            Ok(_) => panic!("parsed a malformed map file"),
+        }
+    }
+
+    const SHRINE: &str = "\
+// A comment
+name: shrine
+weight: 3
+biomes: Forest, RicePaddy
+
+legend:
+# rock
+. earth
+O earth monster=Oni
+o earth item=rice_ball
+灯 earth light=lantern
+
+map:
+
+ ###
+#.O.#
+ o灯
+";
+
+    #[test]
+    fn parses_header_legend_and_map() {
+        let map = MapFile::parse(SHRINE).unwrap();
+        assert_eq!(map.name, "shrine");
+        assert_eq!(map.weight(), 3);
+        assert!(map.suits_biome(Biome::RicePaddy));
+        assert!(!map.suits_biome(Biome::MountainSlope));
+        // Leading and trailing blank rows are trimmed, short rows padded
+        assert_eq!(map.size, IVec2::new(5, 3));
+
+        let terrain = TerrainCatalog::base();
+        let grid = map.to_grid(&terrain, &mut StdRng::seed_from_u64(0));
+        assert_eq!(grid.get(IVec2::new(0, 0)).unwrap().terrain, "void");
+        assert_eq!(grid.get(IVec2::new(1, 0)).unwrap().terrain, "rock");
+        assert_eq!(
+            grid.placements,
+            [
+                (IVec2::new(2, 1), Placement::Monster("Oni".to_string())),
+                (
+                    IVec2::new(1, 2),
+                    Placement::Item {
+                        name: "rice ball".to_string(),
+                        glyph: 'o'
+                    }
+                ),
+                (
+                    IVec2::new(2, 2),
+                    Placement::Light {
+                        kind: LightKind::Lantern,
+                        glyph: '灯'
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn header_lines_need_a_colon() {
+        let (line, message) = parse_error("name: broken\nweight 2\nmap:\n#");
+        assert_eq!(line, 2);
+        assert!(message.starts_with("expected `key: value`"), "{message}");
+    }
+
+    #[test]
+    fn a_map_section_is_required() {
+        let (line, message) = parse_error("name: empty\nlegend:\n# rock\n");
+        assert_eq!(line, 3);
+        assert_eq!(message, "no `map:` section with at least one row");
+
+        let (_, message) = parse_error("legend:\n# rock\nmap:\n\n\n");
+        assert_eq!(message, "no `map:` section with at least one row");
+    }
+
+    #[test]
+    fn map_glyphs_must_be_in_the_legend() {
+        let (line, message) = parse_error("legend:\n# rock\nmap:\n###\n#x#\n");
+        assert_eq!(line, 5);
+        assert_eq!(message, "glyph `x` is not in the legend");
+    }
+
+    #[test]
+    fn legend_lines_are_checked() {
+        let (line, message) = parse_error("legend:\n#\nmap:\n#");
+        assert_eq!(line, 2);
+        assert_eq!(message, "legend glyph `#` has no terrain");
+
+        let (line, message) = parse_error("legend:\n# rock\n* earth light=bonfire\nmap:\n*");
+        assert_eq!(line, 3);
+        assert_eq!(message, "unknown light `bonfire`");
+
+        let (_, message) = parse_error("legend:\n# rock spawn=Oni\nmap:\n#");
+        assert_eq!(
+            message,
+            "expected `monster=`, `item=` or `light=`, found `spawn=Oni`"
+        );
+    }
+
+    #[test]
+    fn shipped_vaults_parse() {
+        let vaults = MapFile::load_dir(Path::new("assets/vaults"));
+        assert_eq!(vaults.len(), 3);
+        assert!(vaults.iter().all(|vault| !vault.name.is_empty()));
+    }
+}
@@ -1,2 +1,13 @@
-pub mod terrain;
+pub mod biome;
+pub mod chunk;
+pub mod connectivity;
+pub mod dungeon;
+pub mod fov;
 pub mod generation;
+pub mod generator;
+pub mod hydrology;
+pub mod map_file;
+pub mod seasons;
+pub mod terrain;
+pub mod tile_grid;
+pub mod tile_map;
@@ -0,0 +1,142 @@
+use super::terrain::TerrainType;
+use crate::time::{GameClock, Season, DAYS_PER_SEASON};
+use bevy::prelude::*;
+use noise::{NoiseFn, Perlin};
+
+/// Days a season's change takes to spread over the whole map once it starts.
+pub const SPREAD_DAYS: f32 = 7.0;
+/// Size of the patches that turn together; larger scales give smaller ones.
+const FRONT_SCALE: f64 = 0.04;
+/// Scale of the finer noise that roughens the edges of the patches.
+const EDGE_SCALE: f64 = 0.3;
+/// Share of a tile's turn set by the finer noise.
+const EDGE_WEIGHT: f64 = 0.25;
+
+/// Decides when each tile takes on a new season's look, so a change spreads
+/// across the world in ragged patches over several days instead of flipping
+/// everywhere at once. It follows the world seed, so the blossom comes to the
+/// same groves first in the same world.
+pub struct SeasonFront {
+    patches: Perlin,
+    edges: Perlin,
+}
+
+impl SeasonFront {
+    pub fn new(seed: u64) -> Self {
+        let noise_seed = (seed ^ (seed >> 32)) as u32;
+        Self {
+            patches: Perlin::new(noise_seed.wrapping_add(3)),
+            edges: Perlin::new(noise_seed.wrapping_add(4)),
+        }
+    }
+
+    /// How far through the spread of a change the tile at `pos` turns, from
+    /// 0 (among the first) to 1 (the last).
+    fn turn(&self, pos: IVec2) -> f32 {
+        let sample = |perlin: &Perlin, scale: f64| {
+            let value = perlin.get([pos.x as f64 * scale, pos.y as f64 * scale]);
+            ((value + 1.0) / 2.0).clamp(0.0, 1.0)
+        };
+        let patches = sample(&self.patches, FRONT_SCALE);
+        let edges = sample(&self.edges, EDGE_SCALE);
+        (patches * (1.0 - EDGE_WEIGHT) + edges * EDGE_WEIGHT) as f32
+    }
+
+    /// The season whose look the tile of `terrain` at `pos` should wear at
+    /// `clock`: the current season's once its change has spread this far,
+    /// otherwise whatever the tile wore when the season before ended. The
+    /// game's first season counts as fully spread, since the clock never
+    /// passed through one before it. `None` for the terrain's usual look.
+    pub fn season_at(
+        &self,
+        clock: &GameClock,
+        pos: IVec2,
+        terrain: &TerrainType,
+    ) -> Option<Season> {
+        if terrain.seasons.is_empty() {
+            return None;
+        }
+        let turn = self.turn(pos);
+        let mut season = clock.season();
+        let mut days = clock.days_into_season();
+        let mut passed = clock.day() / DAYS_PER_SEASON;
+
+        // Look back at most a year, for looks whose change never arrives
+        for _ in 0..4 {
+            let after = terrain.seasons.get(&season).map_or(0.0, |look| look.after);
+            let reached = if passed == 0 {
+                days >= after
+            } else {
+                turn < (days - after) / SPREAD_DAYS
+            };
+            if reached {
+                return terrain.seasons.contains_key(&season).then_some(season);
+            }
+            if passed == 0 {
+                return None;
+            }
+            season = season.previous();
+            days = DAYS_PER_SEASON as f32;
+            passed -= 1;
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::terrain::TerrainCatalog;
+    use crate::time::TICKS_PER_DAY;
+
+    /// The look of `terrain` on every tile of a patch of map, `days` days
+    /// into the game.
+    fn looks(terrain: &str, days: f32) -> Vec<Option<Season>> {
+        let catalog = TerrainCatalog::base();
+        let terrain = catalog.get(terrain);
+        let clock = GameClock {
+            tick: (days * TICKS_PER_DAY as f32) as usize,
+        };
+        let front = SeasonFront::new(11);
+        (0..40)
+            .flat_map(|y| (0..40).map(move |x| IVec2::new(x, y)))
+            .map(|pos| front.season_at(&clock, pos, terrain))
+            .collect()
+    }
+
+    const WINTER: f32 = DAYS_PER_SEASON as f32 * 3.0;
+    const YEAR: f32 = DAYS_PER_SEASON as f32 * 4.0;
+
+    #[test]
+    fn the_first_season_is_worn_from_the_start() {
+        assert!(looks("grass", 0.0)
+            .iter()
+            .all(|&look| look == Some(Season::Spring)));
+        // Nothing is left over from a winter the game never had
+        assert!(looks("pond", 0.0).iter().all(Option::is_none));
+        assert!(looks("rock", WINTER + 20.0).iter().all(Option::is_none));
+    }
+
+    #[test]
+    fn a_change_waits_for_its_look_then_spreads() {
+        // Ponds only start to freeze a fortnight into winter
+        assert!(looks("pond", WINTER + 13.0).iter().all(Option::is_none));
+
+        let spreading = looks("pond", WINTER + 14.0 + SPREAD_DAYS / 2.0);
+        assert!(spreading.contains(&Some(Season::Winter)));
+        assert!(spreading.contains(&None));
+
+        let frozen = looks("pond", WINTER + 14.0 + SPREAD_DAYS);
+        assert!(frozen.iter().all(|&look| look == Some(Season::Winter)));
+    }
+
+    #[test]
+    fn the_last_look_lingers_until_the_next_arrives() {
+        // Spring has only just come round again, so the ice hasn't thawed
+        let thawing = looks("pond", YEAR);
+        assert!(thawing.iter().all(|&look| look == Some(Season::Winter)));
+        assert!(looks("pond", YEAR + SPREAD_DAYS)
+            .iter()
+            .all(Option::is_none));
+    }
+}
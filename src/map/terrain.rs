@@ -1,11 +1,170 @@
-use crate::components::MapItem;
-use bevy::prelude::*;
+use crate::{
+    components::{MapItem, Material},
+    data::{read_ron, validate_entries, DataLoadError},
+    time::Season,
+};
+use bevy::{
+    asset::{io::Reader, AssetLoader, LoadContext},
+    prelude::*,
+    utils::HashMap,
+};
+use rand::Rng;
+use serde::Deserialize;
 
-#[derive(Clone)]
+/// Which way a staircase leads.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub enum Stairs {
+    Up,
+    Down,
+}
+
+/// A terrain's name in both of the game's languages.
+#[derive(Clone, Debug, Deserialize)]
+#[allow(dead_code)]
+pub struct TerrainName {
+    pub en: String,
+    pub ja: String,
+}
+
+/// One entry of a `.terrain.ron` file, before its colors are converted.
+#[derive(Deserialize)]
+struct TerrainDefinition {
+    id: String,
+    name: TerrainName,
+    description: String,
+    glyphs: Vec<String>,
+    colors: Vec<(f32, f32, f32)>,
+    solid: bool,
+    #[serde(default)]
+    water: bool,
+    #[serde(default)]
+    stairs: Option<Stairs>,
+    #[serde(default)]
+    opacity: Option<f32>,
+    #[serde(default)]
+    height: Option<f32>,
+    #[serde(default)]
+    emission: (f32, f32, f32),
+    #[serde(default = "default_one")]
+    reflectance: f32,
+    #[serde(default = "default_one")]
+    movement_cost: f32,
+    #[serde(default)]
+    seasons: HashMap<Season, SeasonalDefinition>,
+}
+
+/// How a terrain changes in one season. Anything left out keeps the
+/// terrain's usual value.
+#[derive(Deserialize)]
+struct SeasonalDefinition {
+    #[serde(default)]
+    after: f32,
+    #[serde(default)]
+    glyphs: Option<Vec<String>>,
+    #[serde(default)]
+    colors: Option<Vec<(f32, f32, f32)>>,
+    #[serde(default)]
+    water: Option<bool>,
+    #[serde(default)]
+    movement_cost: Option<f32>,
+}
+
+fn default_one() -> f32 {
+    1.0
+}
+
+/// Check that terrain definitions can be drawn and that their numbers make
+/// sense: a step always takes some time, and opacity and reflectance are
+/// shares from 0 to 1.
+fn validate_definitions(definitions: &[TerrainDefinition]) -> Result<(), DataLoadError> {
+    validate_entries(definitions.iter().map(|definition| {
+        let id = definition.id.as_str();
+        (id, definition.glyphs.len(), definition.colors.len())
+    }))?;
+
+    let is_cost = |cost: f32| cost.is_finite() && cost > 0.0;
+    let is_share = |share: f32| (0.0..=1.0).contains(&share);
+    for definition in definitions {
+        let invalid = |message: String| {
+            Err(DataLoadError::Invalid(format!(
+                "`{}` {}",
+                definition.id, message
+            )))
+        };
+        if !is_cost(definition.movement_cost) {
+            return invalid(format!(
+                "needs a positive movement_cost, found {}",
+                definition.movement_cost
+            ));
+        }
+        if let Some(opacity) = definition.opacity.filter(|&opacity| !is_share(opacity)) {
+            return invalid(format!("needs an opacity from 0 to 1, found {}", opacity));
+        }
+        if !is_share(definition.reflectance) {
+            return invalid(format!(
+                "needs a reflectance from 0 to 1, found {}",
+                definition.reflectance
+            ));
+        }
+        for (season, look) in &definition.seasons {
+            let glyphs = look.glyphs.as_ref().map_or(1, Vec::len);
+            let colors = look.colors.as_ref().map_or(1, Vec::len);
+            if glyphs == 0 || colors == 0 {
+                return invalid(format!(
+                    "needs at least one glyph and one color in {:?}",
+                    season
+                ));
+            }
+            if let Some(cost) = look.movement_cost.filter(|&cost| !is_cost(cost)) {
+                return invalid(format!(
+                    "needs a positive movement_cost in {:?}, found {}",
+                    season, cost
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn to_colors(colors: Vec<(f32, f32, f32)>) -> Vec<Color> {
+    colors
+        .into_iter()
+        .map(|(r, g, b)| Color::srgb(r, g, b))
+        .collect()
+}
+
+/// What a terrain looks like, and how it is to cross, in one season.
+#[derive(Clone, Debug)]
+pub struct SeasonalLook {
+    /// Days into the season before the look starts spreading, for changes
+    /// that wait for its depths, like ponds freezing over.
+    pub after: f32,
+    pub characters: Vec<String>,
+    pub colors: Vec<Color>,
+    pub water: bool,
+    pub movement_cost: f32,
+}
+
+#[derive(Clone, Debug)]
 pub struct TerrainType {
-    pub characters: Vec<&'static str>,
+    pub id: String,
+    #[allow(dead_code)]
+    pub name: TerrainName,
+    #[allow(dead_code)]
+    pub description: String,
+    pub characters: Vec<String>,
     pub colors: Vec<Color>,
     pub solid: bool,
+    /// Walkable but special: rivers and ponds that swimmers and water yokai
+    /// care about.
+    pub water: bool,
+    pub stairs: Option<Stairs>,
+    pub material: Material,
+    /// Turns it takes to step onto the terrain.
+    pub movement_cost: f32,
+    /// How the terrain changes with the seasons. Seasons not listed keep its
+    /// usual look.
+    pub seasons: HashMap<Season, SeasonalLook>,
 }
 
 impl TerrainType {
@@ -13,59 +172,240 @@ impl TerrainType {
         self.solid
     }
 
-    pub fn to_map_item(&self) -> MapItem {
-        MapItem::new(
-            self.characters.iter().map(|&s| s.to_string()).collect(),
-            self.colors.clone(),
-            self.solid,
-        )
+    /// Dress a tile of this terrain in its look for `season`, or in its usual
+    /// look for `None` or a season without one of its own.
+    pub fn dress(&self, item: &mut MapItem, season: Option<Season>) {
+        match season.and_then(|season| self.seasons.get(&season)) {
+            Some(look) => {
+                item.character_variants.clone_from(&look.characters);
+                item.color_variants.clone_from(&look.colors);
+                item.water = look.water;
+                item.movement_cost = look.movement_cost;
+            }
+            None => {
+                item.character_variants.clone_from(&self.characters);
+                item.color_variants.clone_from(&self.colors);
+                item.water = self.water;
+                item.movement_cost = self.movement_cost;
+            }
+        }
+        item.season = season;
+    }
+
+    pub fn to_map_item(&self, rng: &mut impl Rng) -> MapItem {
+        MapItem {
+            material: self.material,
+            movement_cost: self.movement_cost,
+            terrain: self.id.clone(),
+            ..MapItem::new(
+                self.characters.clone(),
+                self.colors.clone(),
+                self.is_solid(),
+                self.water,
+                self.stairs,
+                rng,
+            )
+        }
     }
 }
 
-pub fn grass() -> TerrainType {
-    TerrainType {
-        characters: vec!["'", ",", "."],
-        colors: vec![
-            Color::srgb(0.2, 0.6, 0.2),
-            Color::srgb(0.3, 0.5, 0.2),
-            Color::srgb(0.25, 0.55, 0.25),
-        ],
-        solid: false,
+impl From<TerrainDefinition> for TerrainType {
+    fn from(definition: TerrainDefinition) -> Self {
+        let plain = Material::new(definition.solid);
+        let (r, g, b) = definition.emission;
+        let material = Material {
+            opacity: definition.opacity.unwrap_or(plain.opacity),
+            height: definition.height.unwrap_or(plain.height),
+            emission: Vec3::new(r, g, b),
+            reflectance: definition.reflectance,
+        };
+        let colors = to_colors(definition.colors);
+        let seasons = definition
+            .seasons
+            .into_iter()
+            .map(|(season, look)| {
+                let look = SeasonalLook {
+                    after: look.after,
+                    characters: look.glyphs.unwrap_or_else(|| definition.glyphs.clone()),
+                    colors: look.colors.map_or_else(|| colors.clone(), to_colors),
+                    water: look.water.unwrap_or(definition.water),
+                    movement_cost: look.movement_cost.unwrap_or(definition.movement_cost),
+                };
+                (season, look)
+            })
+            .collect();
+        Self {
+            id: definition.id,
+            name: definition.name,
+            description: definition.description,
+            characters: definition.glyphs,
+            colors,
+            solid: definition.solid,
+            water: definition.water,
+            stairs: definition.stairs,
+            material,
+            movement_cost: definition.movement_cost,
+            seasons,
+        }
     }
 }
 
-pub fn rock() -> TerrainType {
-    TerrainType {
-        characters: vec!["石", "岩", "磐"],
-        colors: vec![
-            Color::srgb(0.5, 0.5, 0.5),
-            Color::srgb(0.4, 0.4, 0.4),
-            Color::srgb(0.45, 0.45, 0.45),
-        ],
-        solid: true,
+/// Every terrain type the game knows, loaded from `.terrain.ron` assets and
+/// looked up by id. Generators and map files only ever name terrain, so new
+/// tiles can be added by editing the asset.
+#[derive(Asset, Resource, TypePath, Clone)]
+pub struct TerrainCatalog {
+    terrain: HashMap<String, TerrainType>,
+    /// Stands in for ids missing from the catalog, drawn so they stand out.
+    unknown: TerrainType,
+}
+
+impl TerrainCatalog {
+    pub fn new(terrain: impl IntoIterator<Item = TerrainType>) -> Self {
+        Self {
+            terrain: terrain
+                .into_iter()
+                .map(|terrain| (terrain.id.clone(), terrain))
+                .collect(),
+            unknown: TerrainType {
+                id: String::from("unknown"),
+                name: TerrainName {
+                    en: String::from("unknown terrain"),
+                    ja: String::from("不明"),
+                },
+                description: String::from("Terrain missing from the catalog."),
+                characters: vec![String::from("?")],
+                colors: vec![Color::srgb(1.0, 0.0, 1.0)],
+                solid: false,
+                water: false,
+                stairs: None,
+                material: Material::new(false),
+                movement_cost: 1.0,
+                seasons: HashMap::new(),
+            },
+        }
+    }
+
+    /// The terrain with `id`, or a magenta `?` placeholder if there is none.
+    pub fn get(&self, id: &str) -> &TerrainType {
+        self.terrain.get(id).unwrap_or(&self.unknown)
+    }
+
+    /// A map item of the terrain with `id`, with a random variant.
+    pub fn item(&self, id: &str, rng: &mut impl Rng) -> MapItem {
+        self.get(id).to_map_item(rng)
+    }
+
+    /// The catalog shipped in `assets/data`, for tests that need real terrain.
+    #[cfg(test)]
+    pub(crate) fn base() -> Self {
+        let definitions: Vec<TerrainDefinition> =
+            ron::from_str(include_str!("../../assets/data/base.terrain.ron"))
+                .expect("base terrain should parse");
+        validate_definitions(&definitions).expect("base terrain should be valid");
+        Self::new(definitions.into_iter().map(TerrainType::from))
     }
 }
 
-pub fn tree() -> TerrainType {
-    TerrainType {
-        characters: vec!["木", "林", "森"],
-        colors: vec![
-            Color::srgb(0.1, 0.4, 0.1),
-            Color::srgb(0.15, 0.45, 0.15),
-            Color::srgb(0.2, 0.5, 0.2),
-        ],
-        solid: true,
+/// Loads a `TerrainCatalog` from a RON list of terrain definitions.
+#[derive(Default)]
+pub struct TerrainCatalogLoader;
+
+impl AssetLoader for TerrainCatalogLoader {
+    type Asset = TerrainCatalog;
+    type Settings = ();
+    type Error = DataLoadError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<TerrainCatalog, DataLoadError> {
+        let definitions: Vec<TerrainDefinition> = read_ron(reader).await?;
+        validate_definitions(&definitions)?;
+
+        Ok(TerrainCatalog::new(
+            definitions.into_iter().map(TerrainType::from),
+        ))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["terrain.ron"]
     }
 }
 
-pub fn earth() -> TerrainType {
-    TerrainType {
-        characters: vec![".", ",", "'"],
-        colors: vec![
-            Color::srgb(0.6, 0.4, 0.2),
-            Color::srgb(0.55, 0.35, 0.15),
-            Color::srgb(0.5, 0.3, 0.1),
-        ],
-        solid: false,
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Validate one terrain entry with `fields` added to a plain grass tile.
+    fn validate(fields: &str) -> Result<(), DataLoadError> {
+        let text = format!(
+            r#"#![enable(implicit_some)]
+            [(
+                id: "grass",
+                name: (en: "grass", ja: "草"),
+                description: "Grass.",
+                glyphs: ["草"],
+                colors: [(0.2, 0.6, 0.2)],
+                solid: false,
+                {fields}
+            )]"#
+        );
+        let definitions: Vec<TerrainDefinition> = ron::from_str(&text).unwrap();
+        validate_definitions(&definitions)
+    }
+
+    fn message(result: Result<(), DataLoadError>) -> String {
+        match result {
+            Err(DataLoadError::Invalid(message)) => message,
+            other => panic!("expected an invalid entry, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn plain_and_seasonal_values_in_range_are_accepted() {
+        assert!(validate("").is_ok());
+        assert!(validate(
+            "movement_cost: 0.5, opacity: 0.0, reflectance: 1.0, \
+             seasons: { Winter: (after: 3.0, movement_cost: 2.0) },"
+        )
+        .is_ok());
+        TerrainCatalog::base();
+    }
+
+    #[test]
+    fn steps_must_take_time() {
+        for cost in ["0.0", "-1.0", "inf", "NaN"] {
+            let result = validate(&format!("movement_cost: {cost},"));
+            assert!(message(result).contains("positive movement_cost"), "{cost}");
+        }
+        let result = validate("seasons: { Winter: (movement_cost: 0.0) },");
+        assert_eq!(
+            message(result),
+            "`grass` needs a positive movement_cost in Winter, found 0"
+        );
+    }
+
+    #[test]
+    fn opacity_and_reflectance_are_shares() {
+        assert_eq!(
+            message(validate("opacity: 1.5,")),
+            "`grass` needs an opacity from 0 to 1, found 1.5"
+        );
+        assert_eq!(
+            message(validate("reflectance: -0.1,")),
+            "`grass` needs a reflectance from 0 to 1, found -0.1"
+        );
+        assert!(message(validate("reflectance: NaN,")).contains("reflectance"));
+    }
+
+    #[test]
+    fn seasonal_looks_need_glyphs_and_colors() {
+        assert_eq!(
+            message(validate("seasons: { Autumn: (glyphs: []) },")),
+            "`grass` needs at least one glyph and one color in Autumn"
+        );
     }
 }
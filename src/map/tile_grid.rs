@@ -0,0 +1,68 @@
+use super::biome::Biome;
+use super::map_file::Placement;
+use crate::components::MapItem;
+use bevy::prelude::*;
+
+/// Plain terrain data produced by a `MapGenerator`, with no Bevy entities.
+/// Tiles are stored row by row from `(0, 0)` at the top left.
+#[derive(Clone)]
+pub struct TileGrid {
+    pub size: IVec2,
+    tiles: Vec<MapItem>,
+    biomes: Vec<Biome>,
+    /// Monsters and items to spawn once the tiles are.
+    pub placements: Vec<(IVec2, Placement)>,
+}
+
+impl TileGrid {
+    /// Build a grid by calling `tile` for every position in row order.
+    pub fn from_fn(size: IVec2, mut tile: impl FnMut(IVec2) -> (MapItem, Biome)) -> Self {
+        let (tiles, biomes) = (0..size.y)
+            .flat_map(|y| (0..size.x).map(move |x| IVec2::new(x, y)))
+            .map(&mut tile)
+            .unzip();
+        Self {
+            size,
+            tiles,
+            biomes,
+            placements: Vec::new(),
+        }
+    }
+
+    pub fn contains(&self, pos: IVec2) -> bool {
+        pos.cmpge(IVec2::ZERO).all() && pos.cmplt(self.size).all()
+    }
+
+    fn index(&self, pos: IVec2) -> Option<usize> {
+        self.contains(pos)
+            .then(|| (pos.y * self.size.x + pos.x) as usize)
+    }
+
+    pub fn get(&self, pos: IVec2) -> Option<&MapItem> {
+        self.index(pos).map(|index| &self.tiles[index])
+    }
+
+    pub fn biome(&self, pos: IVec2) -> Option<Biome> {
+        self.index(pos).map(|index| self.biomes[index])
+    }
+
+    pub fn set(&mut self, pos: IVec2, item: MapItem) {
+        if let Some(index) = self.index(pos) {
+            self.tiles[index] = item;
+        }
+    }
+
+    pub fn place(&mut self, pos: IVec2, placement: Placement) {
+        self.placements.push((pos, placement));
+    }
+
+    /// Every position in the grid, in storage order.
+    pub fn positions(&self) -> impl Iterator<Item = IVec2> {
+        let size = self.size;
+        (0..size.y).flat_map(move |y| (0..size.x).map(move |x| IVec2::new(x, y)))
+    }
+
+    pub fn into_parts(self) -> (Vec<MapItem>, Vec<Biome>) {
+        (self.tiles, self.biomes)
+    }
+}
@@ -0,0 +1,296 @@
+use super::biome::Biome;
+use super::chunk::{chunk_of, chunk_origin, chunk_positions, local_index, Chunk};
+use super::connectivity::flood_fill;
+use super::map_file::Placement;
+use super::terrain::{Stairs, TerrainCatalog};
+use super::tile_grid::TileGrid;
+use crate::{components::MapItem, time::Season};
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use rand::Rng;
+
+/// Terrain stored by integer `(col, row)` in chunks so lookups don't need to
+/// scan every terrain entity. Row 0 is the top of the map and rows grow
+/// downwards; negative positions are valid on the endless overworld.
+#[derive(Resource)]
+pub struct TileMap {
+    pub tile_size: f32,
+    /// World position of the centre of tile `(0, 0)`.
+    pub origin: Vec2,
+    /// Inclusive playable area for finite maps, `None` for the overworld.
+    pub bounds: Option<IRect>,
+    chunks: HashMap<IVec2, Chunk>,
+    /// Monsters and items from map files waiting for their chunk to spawn.
+    placements: Vec<(IVec2, Placement)>,
+}
+
+impl TileMap {
+    pub fn new(origin: Vec2, tile_size: f32, bounds: Option<IRect>) -> Self {
+        Self {
+            tile_size,
+            origin,
+            bounds,
+            chunks: HashMap::default(),
+            placements: Vec::new(),
+        }
+    }
+
+    /// Store a finite generated level, bounded to the grid's size. Chunks
+    /// hanging over the edge are padded with void, its variant drawn from
+    /// `rng`.
+    pub fn from_grid(
+        grid: &TileGrid,
+        terrain: &TerrainCatalog,
+        tile_size: f32,
+        rng: &mut impl Rng,
+    ) -> Self {
+        let bounds = IRect::from_corners(IVec2::ZERO, grid.size - IVec2::ONE);
+        let mut tile_map = Self::new(Vec2::ZERO, tile_size, Some(bounds));
+        let padding = terrain.item("void", rng);
+
+        let last_chunk = chunk_of(bounds.max);
+        for y in 0..=last_chunk.y {
+            for x in 0..=last_chunk.x {
+                let coord = IVec2::new(x, y);
+                let (tiles, biomes) = chunk_positions(coord)
+                    .map(|pos| match (grid.get(pos), grid.biome(pos)) {
+                        (Some(item), Some(biome)) => (item.clone(), biome),
+                        _ => (padding.clone(), Biome::Ruins),
+                    })
+                    .unzip();
+                tile_map.insert_chunk(coord, Chunk::new(tiles, biomes));
+            }
+        }
+
+        tile_map.placements = grid.placements.clone();
+        tile_map
+    }
+
+    pub fn in_bounds(&self, pos: IVec2) -> bool {
+        self.bounds.is_none_or(|bounds| bounds.contains(pos))
+    }
+
+    pub fn chunk(&self, chunk: IVec2) -> Option<&Chunk> {
+        self.chunks.get(&chunk)
+    }
+
+    pub fn chunk_mut(&mut self, chunk: IVec2) -> Option<&mut Chunk> {
+        self.chunks.get_mut(&chunk)
+    }
+
+    pub fn insert_chunk(&mut self, chunk: IVec2, data: Chunk) {
+        self.chunks.insert(chunk, data);
+    }
+
+    /// Store a chunk-sized grid from a generator at `chunk`, keeping its
+    /// placements for when the chunk is spawned.
+    pub fn insert_grid_chunk(&mut self, chunk: IVec2, mut grid: TileGrid) {
+        let origin = chunk_origin(chunk);
+        self.placements.extend(
+            grid.placements
+                .drain(..)
+                .map(|(pos, placement)| (origin + pos, placement)),
+        );
+        self.insert_chunk(chunk, Chunk::from_grid(grid));
+    }
+
+    fn is_spawned(&self, pos: IVec2) -> bool {
+        self.chunk(chunk_of(pos)).is_some_and(Chunk::is_spawned)
+    }
+
+    /// Whether any placements are waiting on chunks that have been spawned.
+    pub fn has_spawned_placements(&self) -> bool {
+        self.placements.iter().any(|(pos, _)| self.is_spawned(*pos))
+    }
+
+    /// Remove and return the placements whose chunks have been spawned.
+    pub fn take_spawned_placements(&mut self) -> Vec<(IVec2, Placement)> {
+        let (ready, waiting) = std::mem::take(&mut self.placements)
+            .into_iter()
+            .partition(|(pos, _)| self.is_spawned(*pos));
+        self.placements = waiting;
+        ready
+    }
+
+    /// Coordinates of every chunk that currently has entities spawned.
+    pub fn spawned_chunks(&self) -> impl Iterator<Item = IVec2> + '_ {
+        self.chunks
+            .iter()
+            .filter(|(_, chunk)| chunk.is_spawned())
+            .map(|(&coord, _)| coord)
+    }
+
+    /// Forget every chunk's entities, e.g. after they were despawned when
+    /// leaving the level. The tiles themselves are kept.
+    pub fn clear_entities(&mut self) {
+        for chunk in self.chunks.values_mut() {
+            chunk.entities = None;
+        }
+    }
+
+    pub fn map_item(&self, pos: IVec2) -> Option<&MapItem> {
+        if !self.in_bounds(pos) {
+            return None;
+        }
+        self.chunks
+            .get(&chunk_of(pos))
+            .map(|chunk| &chunk.tiles[local_index(pos)])
+    }
+
+    fn map_item_mut(&mut self, pos: IVec2) -> Option<&mut MapItem> {
+        if !self.in_bounds(pos) {
+            return None;
+        }
+        self.chunks
+            .get_mut(&chunk_of(pos))
+            .map(|chunk| &mut chunk.tiles[local_index(pos)])
+    }
+
+    /// Dress the tile at `pos` in its terrain's look for `season`, returning
+    /// it as it now is.
+    pub fn dress(
+        &mut self,
+        pos: IVec2,
+        terrain: &TerrainCatalog,
+        season: Option<Season>,
+    ) -> Option<&MapItem> {
+        let item = self.map_item_mut(pos)?;
+        terrain.get(&item.terrain).dress(item, season);
+        Some(item)
+    }
+
+    pub fn biome(&self, pos: IVec2) -> Option<Biome> {
+        if !self.in_bounds(pos) {
+            return None;
+        }
+        self.chunks
+            .get(&chunk_of(pos))
+            .map(|chunk| chunk.biomes[local_index(pos)])
+    }
+
+    pub fn entity(&self, pos: IVec2) -> Option<Entity> {
+        self.chunks
+            .get(&chunk_of(pos))
+            .and_then(|chunk| chunk.entities.as_ref())
+            .map(|entities| entities[local_index(pos)])
+    }
+
+    /// Tiles outside the map, or in chunks not generated yet, count as solid
+    /// so nothing can walk off the edge.
+    pub fn is_solid(&self, pos: IVec2) -> bool {
+        self.map_item(pos).is_none_or(|item| item.solid)
+    }
+
+    /// Rivers and ponds: walkable, but of interest to swimmers and water yokai.
+    pub fn is_water(&self, pos: IVec2) -> bool {
+        self.map_item(pos).is_some_and(|item| item.water)
+    }
+
+    pub fn is_walkable(&self, pos: IVec2) -> bool {
+        !self.is_solid(pos)
+    }
+
+    /// Whether the player has seen the tile at `pos`.
+    pub fn is_explored(&self, pos: IVec2) -> bool {
+        self.map_item(pos).is_some_and(|item| item.explored)
+    }
+
+    /// Remember that the player has seen the tile at `pos`.
+    pub fn explore(&mut self, pos: IVec2) {
+        if let Some(item) = self.map_item_mut(pos) {
+            item.explored = true;
+        }
+    }
+
+    /// How much the tile at `pos` blocks sight and light, from its material.
+    /// Like solidity, anything outside the generated map is opaque.
+    pub fn opacity(&self, pos: IVec2) -> f32 {
+        self.map_item(pos)
+            .map_or(1.0, |item| item.material.sight_blocking())
+    }
+
+    /// Turns it takes to step onto the tile at `pos`, before the weather.
+    pub fn movement_cost(&self, pos: IVec2) -> f32 {
+        self.map_item(pos).map_or(1.0, |item| item.movement_cost)
+    }
+
+    /// Closest walkable position to `pos`, searching outwards in rings up to
+    /// `radius` tiles away.
+    pub fn nearest_walkable(&self, pos: IVec2, radius: i32) -> Option<IVec2> {
+        (0..=radius).find_map(|ring| {
+            (-ring..=ring)
+                .flat_map(|dy| (-ring..=ring).map(move |dx| pos + IVec2::new(dx, dy)))
+                .filter(|&candidate| (candidate - pos).abs().max_element() == ring)
+                .find(|&candidate| self.is_walkable(candidate))
+        })
+    }
+
+    /// Position of a staircase in the generated chunks, if there is one. With
+    /// several, the first in row order of chunks and tiles.
+    pub fn find_stairs(&self, stairs: Stairs) -> Option<IVec2> {
+        // Sort so a level with several staircases always picks the same one
+        let mut chunks: Vec<(&IVec2, &Chunk)> = self.chunks.iter().collect();
+        chunks.sort_by_key(|(chunk, _)| (chunk.y, chunk.x));
+        chunks
+            .into_iter()
+            .flat_map(|(&coord, chunk)| chunk_positions(coord).zip(chunk.tiles.iter()))
+            .find(|(_, item)| item.stairs == Some(stairs))
+            .map(|(pos, _)| pos)
+    }
+
+    /// Every walkable position in the spawned chunks.
+    pub fn walkable_positions(&self) -> Vec<IVec2> {
+        // Sort so iteration order (and so seeded spawning) is reproducible
+        let mut chunks: Vec<IVec2> = self.spawned_chunks().collect();
+        chunks.sort_by_key(|chunk| (chunk.y, chunk.x));
+        chunks
+            .into_iter()
+            .flat_map(chunk_positions)
+            .filter(|&pos| self.is_walkable(pos))
+            .collect()
+    }
+
+    /// Walkable positions in the spawned chunks that can be reached on foot
+    /// from `start`, in the same order as `walkable_positions`.
+    pub fn reachable_from(&self, start: IVec2) -> Vec<IVec2> {
+        let mut region = flood_fill(start, |pos| self.is_walkable(pos) && self.is_spawned(pos));
+        region.sort_by_key(|pos| (chunk_of(*pos).y, chunk_of(*pos).x, local_index(*pos)));
+        region
+    }
+
+    /// The largest area of the spawned chunks that is connected on foot.
+    pub fn largest_region(&self) -> Vec<IVec2> {
+        let mut seen = HashSet::new();
+        let mut largest = Vec::new();
+        for pos in self.walkable_positions() {
+            if seen.contains(&pos) {
+                continue;
+            }
+            let region = self.reachable_from(pos);
+            seen.extend(region.iter().copied());
+            if region.len() > largest.len() {
+                largest = region;
+            }
+        }
+        largest
+    }
+
+    pub fn grid_to_world(&self, pos: IVec2) -> Vec2 {
+        Vec2::new(
+            self.origin.x + pos.x as f32 * self.tile_size,
+            self.origin.y - pos.y as f32 * self.tile_size,
+        )
+    }
+
+    /// World-space rectangle covered by a finite map, including the outer
+    /// half of the edge tiles.
+    pub fn world_bounds(&self) -> Option<Rect> {
+        self.bounds.map(|bounds| {
+            let top_left = self.grid_to_world(bounds.min);
+            let bottom_right = self.grid_to_world(bounds.max);
+            Rect::from_corners(top_left, bottom_right).inflate(self.tile_size / 2.0)
+        })
+    }
+}
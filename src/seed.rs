@@ -0,0 +1,41 @@
+use bevy::prelude::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+/// Seed every piece of world generation is derived from, so a reported seed
+/// reproduces the same map, monster placement and player start.
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WorldSeed(pub u64);
+
+impl WorldSeed {
+    /// Use `--seed <n>` from the command line, then the `YOKAI_SEED`
+    /// environment variable, and otherwise pick a random seed.
+    pub fn from_env() -> Self {
+        let args: Vec<String> = std::env::args().collect();
+        let from_args = args
+            .iter()
+            .position(|arg| arg == "--seed")
+            .and_then(|index| args.get(index + 1))
+            .cloned();
+        let from_var = std::env::var("YOKAI_SEED").ok();
+
+        match from_args.or(from_var).map(|value| value.parse::<u64>()) {
+            Some(Ok(seed)) => Self(seed),
+            Some(Err(err)) => {
+                println!("Ignoring invalid world seed ({err}), using a random one");
+                Self(rand::thread_rng().gen())
+            }
+            None => Self(rand::thread_rng().gen()),
+        }
+    }
+}
+
+/// The single RNG used by generation and spawning. Systems must draw from it
+/// in a fixed order for a seed to stay reproducible.
+#[derive(Resource)]
+pub struct WorldRng(pub StdRng);
+
+impl WorldRng {
+    pub fn new(seed: WorldSeed) -> Self {
+        Self(StdRng::seed_from_u64(seed.0))
+    }
+}
@@ -7,7 +7,7 @@ use std::sync::{Arc, Mutex};
 #[derive(Resource)]
 pub struct AudioState {
     _stream: Arc<OutputStream>,
-    _stream_handle: Arc<OutputStreamHandle>, 
+    _stream_handle: Arc<OutputStreamHandle>,
     sink: Arc<Mutex<Sink>>,
 }
 
@@ -16,6 +16,8 @@ unsafe impl Send for AudioState {}
 unsafe impl Sync for AudioState {}
 
 impl AudioState {
+    // rodio's OutputStream isn't Send/Sync; see the unsafe impls above
+    #[allow(clippy::arc_with_non_send_sync)]
     pub fn new() -> Self {
         let (stream, stream_handle) = OutputStream::try_default().unwrap();
         let sink = Sink::try_new(&stream_handle).unwrap();
@@ -33,7 +35,7 @@ impl AudioState {
         let start_volume = sink.lock().unwrap().volume();
         let volume_step = start_volume / steps as f32;
         let sleep_duration = duration / steps as f32;
-        
+
         std::thread::spawn(move || {
             for _ in 0..steps {
                 if let Ok(sink) = sink.lock() {
@@ -51,13 +53,13 @@ impl AudioState {
 
 pub fn setup_audio(mut commands: Commands) {
     let audio_state = AudioState::new();
-    
+
     // Load and play the intro music
     let file = BufReader::new(File::open("src/soundtrack_1.mp3").unwrap());
     let source = Decoder::new(file).unwrap();
     if let Ok(sink) = audio_state.sink.lock() {
         sink.append(source);
     }
-    
+
     commands.insert_resource(audio_state);
 }
@@ -0,0 +1,67 @@
+use crate::{components::*, map::tile_map::TileMap, MainCamera};
+use bevy::{prelude::*, window::PrimaryWindow};
+
+/// Keep the main camera centred on the player, stopping at the edges of
+/// finite maps so the view never scrolls into empty space.
+pub fn camera_follow_player(
+    tile_map: Option<Res<TileMap>>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    player_query: Query<&Transform, (With<PlayerBody>, Without<MainCamera>)>,
+    mut camera_query: Query<&mut Transform, With<MainCamera>>,
+) {
+    let Some(tile_map) = tile_map else {
+        return;
+    };
+    let (Ok(window), Ok(player), Ok(mut camera)) = (
+        window_query.get_single(),
+        player_query.get_single(),
+        camera_query.get_single_mut(),
+    ) else {
+        return;
+    };
+
+    camera.translation.x = player.translation.x;
+    camera.translation.y = player.translation.y;
+
+    // The endless overworld has no edges to stop at
+    let Some(bounds) = tile_map.world_bounds() else {
+        return;
+    };
+    let half_view = window.size() / 2.0;
+    camera.translation.x = clamp_to_bounds(
+        player.translation.x,
+        bounds.min.x,
+        bounds.max.x,
+        half_view.x,
+    );
+    camera.translation.y = clamp_to_bounds(
+        player.translation.y,
+        bounds.min.y,
+        bounds.max.y,
+        half_view.y,
+    );
+}
+
+/// Clamp a camera coordinate so `target ± half_view` stays inside
+/// `min..max`, centring on the map when it is smaller than the view.
+fn clamp_to_bounds(target: f32, min: f32, max: f32, half_view: f32) -> f32 {
+    if max - min <= half_view * 2.0 {
+        (min + max) / 2.0
+    } else {
+        target.clamp(min + half_view, max - half_view)
+    }
+}
+
+/// Pin the combat message to the bottom of the window when it is resized.
+pub fn anchor_combat_message(
+    window_query: Query<&Window, (With<PrimaryWindow>, Changed<Window>)>,
+    mut message_query: Query<&mut Transform, With<CombatMessage>>,
+) {
+    let Ok(window) = window_query.get_single() else {
+        return;
+    };
+
+    for mut transform in message_query.iter_mut() {
+        transform.translation.y = -window.height() / 2.0 + 12.0;
+    }
+}
@@ -0,0 +1,149 @@
+use crate::{
+    components::*,
+    create_text_color_bundle,
+    map::{
+        chunk::{chunk_of, chunk_positions, CHUNK_SIZE},
+        generator::{level_seed, MapGenerator, MapGeneratorRegistry},
+        terrain::TerrainCatalog,
+        tile_map::TileMap,
+    },
+    seed::WorldSeed,
+    systems::level::CurrentLevel,
+    TerrainEntity,
+};
+use bevy::{prelude::*, window::PrimaryWindow};
+
+/// Tiles beyond the edge of the window that are kept spawned.
+const LOAD_MARGIN: i32 = CHUNK_SIZE;
+/// Chunks further than this beyond the window are despawned. Larger than
+/// `LOAD_MARGIN` so pacing across a chunk border doesn't respawn it.
+const UNLOAD_MARGIN: i32 = CHUNK_SIZE * 2;
+
+/// Half the window size in tiles.
+pub fn half_view_tiles(window: &Window, tile_size: f32) -> IVec2 {
+    (window.size() / (2.0 * tile_size)).ceil().as_ivec2()
+}
+
+/// Chunk coordinates overlapping the tiles within `half_extent` of `center`.
+fn chunks_around(center: IVec2, half_extent: IVec2) -> impl Iterator<Item = IVec2> {
+    let min = chunk_of(center - half_extent);
+    let max = chunk_of(center + half_extent);
+    (min.y..=max.y).flat_map(move |y| (min.x..=max.x).map(move |x| IVec2::new(x, y)))
+}
+
+/// Generate and spawn every chunk within view of `center`. Chunks streamed
+/// out earlier are respawned from their stored tiles rather than regenerated.
+#[allow(clippy::too_many_arguments)]
+pub fn load_chunks_around(
+    commands: &mut Commands,
+    tile_map: &mut TileMap,
+    generator: &dyn MapGenerator,
+    terrain: &TerrainCatalog,
+    seed: u64,
+    font: &Handle<Font>,
+    center: IVec2,
+    half_view: IVec2,
+) {
+    for coord in chunks_around(center, half_view + IVec2::splat(LOAD_MARGIN)) {
+        // Finite maps are generated up front; only endless ones grow
+        if tile_map.chunk(coord).is_none() && tile_map.bounds.is_none() {
+            if let Some(grid) = generator.generate_chunk(terrain, seed, coord) {
+                tile_map.insert_grid_chunk(coord, grid);
+            }
+        }
+        if tile_map
+            .chunk(coord)
+            .is_some_and(|chunk| !chunk.is_spawned())
+        {
+            spawn_chunk(commands, tile_map, font, coord);
+        }
+    }
+}
+
+fn spawn_chunk(commands: &mut Commands, tile_map: &mut TileMap, font: &Handle<Font>, coord: IVec2) {
+    let Some(chunk) = tile_map.chunk(coord) else {
+        return;
+    };
+
+    let entities = chunk_positions(coord)
+        .zip(chunk.tiles.iter())
+        .map(|(position, map_item)| {
+            let world = tile_map.grid_to_world(position);
+            commands
+                .spawn((
+                    create_text_color_bundle(
+                        font.clone(),
+                        map_item.current_character(),
+                        world.x,
+                        world.y,
+                        0.0,
+                        map_item.current_color(),
+                    ),
+                    BaseColor(map_item.current_color()),
+                    map_item.clone(),
+                    GridPosition::from(position),
+                    TerrainEntity,
+                ))
+                .id()
+        })
+        .collect();
+
+    if let Some(chunk) = tile_map.chunk_mut(coord) {
+        chunk.entities = Some(entities);
+    }
+}
+
+fn despawn_chunk(commands: &mut Commands, tile_map: &mut TileMap, coord: IVec2) {
+    let entities = tile_map
+        .chunk_mut(coord)
+        .and_then(|chunk| chunk.entities.take());
+    for entity in entities.into_iter().flatten() {
+        commands.entity(entity).despawn();
+    }
+}
+
+/// Spawn chunks the player is approaching and despawn ones left far behind.
+/// Despawned chunks keep their tiles in the `TileMap`.
+#[allow(clippy::too_many_arguments)]
+pub fn stream_chunks(
+    mut commands: Commands,
+    tile_map: Option<ResMut<TileMap>>,
+    registry: Res<MapGeneratorRegistry>,
+    terrain: Res<TerrainCatalog>,
+    current_level: Res<CurrentLevel>,
+    seed: Res<WorldSeed>,
+    asset_server: Res<AssetServer>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    player_query: Query<&GridPosition, (With<PlayerBody>, Changed<GridPosition>)>,
+) {
+    let Some(mut tile_map) = tile_map else {
+        return;
+    };
+    let (Ok(player), Ok(window)) = (player_query.get_single(), window_query.get_single()) else {
+        return;
+    };
+
+    let center = IVec2::from(*player);
+    let half_view = half_view_tiles(window, tile_map.tile_size);
+    let font = asset_server.load("fonts/NotoSansJP-VariableFont_wght.ttf");
+    load_chunks_around(
+        &mut commands,
+        &mut tile_map,
+        registry.generator_for_depth(current_level.depth),
+        &terrain,
+        level_seed(seed.0, current_level.depth),
+        &font,
+        center,
+        half_view,
+    );
+
+    let keep_min = chunk_of(center - half_view - IVec2::splat(UNLOAD_MARGIN));
+    let keep_max = chunk_of(center + half_view + IVec2::splat(UNLOAD_MARGIN));
+    let far_chunks: Vec<IVec2> = tile_map
+        .spawned_chunks()
+        .filter(|coord| coord.cmplt(keep_min).any() || coord.cmpgt(keep_max).any())
+        .collect();
+    for coord in far_chunks {
+        despawn_chunk(&mut commands, &mut tile_map, coord);
+    }
+}
@@ -0,0 +1,76 @@
+use crate::{
+    components::CombatMessage,
+    time::{DayChanged, GameClock, PeriodChanged, Season, SeasonChanged, TimePassed, TimePeriod},
+};
+use bevy::prelude::*;
+
+/// Advance the clock by the ticks the scheduler ran the world on for,
+/// announcing each new period, day and season as it begins.
+pub fn tick_clock(
+    mut passed: EventReader<TimePassed>,
+    mut clock: ResMut<GameClock>,
+    mut period_events: EventWriter<PeriodChanged>,
+    mut day_events: EventWriter<DayChanged>,
+    mut season_events: EventWriter<SeasonChanged>,
+) {
+    for _ in passed.read().flat_map(|passed| 0..passed.ticks) {
+        let before = *clock;
+        clock.advance();
+
+        if clock.period() != before.period() {
+            period_events.send(PeriodChanged {
+                period: clock.period(),
+            });
+        }
+        if clock.day() != before.day() {
+            day_events.send(DayChanged { day: clock.day() });
+        }
+        if clock.season() != before.season() {
+            season_events.send(SeasonChanged {
+                season: clock.season(),
+            });
+        }
+    }
+}
+
+/// Tell the player when the time of day or the season turns, and log the
+/// calendar at debug level as each day begins.
+pub fn announce_time(
+    clock: Res<GameClock>,
+    mut period_events: EventReader<PeriodChanged>,
+    mut day_events: EventReader<DayChanged>,
+    mut season_events: EventReader<SeasonChanged>,
+    mut message_query: Query<(&mut Text2d, &mut CombatMessage)>,
+) {
+    for event in day_events.read() {
+        debug!(
+            "Day {} (day {} of the year): {:?}, {:?} moon",
+            event.day,
+            clock.day_of_year(),
+            clock.season(),
+            clock.moon_phase()
+        );
+    }
+
+    let period = period_events.read().last().map(|event| match event.period {
+        TimePeriod::Night => "Night falls.",
+        TimePeriod::WitchingHour => "The witching hour is upon you.",
+        TimePeriod::Dawn => "The sky pales towards dawn.",
+        TimePeriod::Day => "The sun is up.",
+        TimePeriod::Dusk => "The sun sinks towards the hills.",
+    });
+    let season = season_events.read().last().map(|event| match event.season {
+        Season::Spring => "Spring has come.",
+        Season::Summer => "Summer has come.",
+        Season::Autumn => "Autumn has come.",
+        Season::Winter => "Winter has come.",
+    });
+
+    // A new season outranks the hour, as it's the rarer news
+    if let Some(notice) = season.or(period) {
+        if let Ok((mut text, mut message)) = message_query.get_single_mut() {
+            message.message = String::from(notice);
+            text.0 = message.message.clone();
+        }
+    }
+}
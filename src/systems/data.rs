@@ -0,0 +1,49 @@
+use crate::{bestiary::Bestiary, map::terrain::TerrainCatalog};
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+/// Keeps a data asset loaded so `sync_data_asset` can find it.
+#[derive(Resource)]
+pub struct DataHandle<T: Asset>(pub Handle<T>);
+
+pub fn load_data_assets(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let terrain: Handle<TerrainCatalog> = asset_server.load("data/base.terrain.ron");
+    let bestiary: Handle<Bestiary> = asset_server.load("data/base.bestiary.ron");
+    commands.insert_resource(DataHandle(terrain));
+    commands.insert_resource(DataHandle(bestiary));
+}
+
+/// Copy a data asset into the resource of the same type that game systems
+/// read, whenever it finishes loading or is edited on disk.
+pub fn sync_data_asset<T: Asset + Resource + Clone>(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<T>>,
+    handle: Option<Res<DataHandle<T>>>,
+    assets: Res<Assets<T>>,
+) {
+    let Some(handle) = handle else {
+        return;
+    };
+    for event in events.read() {
+        if event.is_loaded_with_dependencies(&handle.0) || event.is_modified(&handle.0) {
+            if let Some(asset) = assets.get(&handle.0) {
+                if let Some(path) = handle.0.path() {
+                    println!("Loaded {}", path);
+                }
+                commands.insert_resource(asset.clone());
+            }
+        }
+    }
+}
+
+/// The data assets the game can't start without.
+#[derive(SystemParam)]
+pub struct GameData<'w> {
+    terrain: Option<Res<'w, TerrainCatalog>>,
+    bestiary: Option<Res<'w, Bestiary>>,
+}
+
+impl GameData<'_> {
+    pub fn is_loaded(&self) -> bool {
+        self.terrain.is_some() && self.bestiary.is_some()
+    }
+}
@@ -0,0 +1,74 @@
+use crate::{
+    bestiary::{Ability, SpawnConditions},
+    map::tile_map::TileMap,
+    time::GameClock,
+    weather::{Weather, WeatherKind},
+};
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+/// Snow at least this heavy takes footprints.
+const TRACK_SNOW: f32 = 0.3;
+
+/// How the hour, the season and the weather bear on the rules right now.
+/// Movement, combat, sight and spawning all ask it rather than reading the
+/// clock and the weather themselves, so they agree on what the weather does.
+#[derive(SystemParam)]
+pub struct Environment<'w> {
+    clock: Res<'w, GameClock>,
+    weather: Res<'w, Weather>,
+}
+
+impl Environment<'_> {
+    pub fn clock(&self) -> &GameClock {
+        &self.clock
+    }
+
+    pub fn weather(&self) -> &Weather {
+        &self.weather
+    }
+
+    /// Whether the weather has shifted since the system last ran.
+    pub fn weather_changed(&self) -> bool {
+        self.weather.is_changed()
+    }
+
+    /// Ticks it takes to step onto the tile at `pos`: the terrain's cost,
+    /// raised by snow and storms.
+    pub fn movement_cost(&self, tile_map: &TileMap, pos: IVec2) -> f32 {
+        tile_map.movement_cost(pos) * self.weather.movement_multiplier()
+    }
+
+    /// Multiplier on how far anyone can see.
+    pub fn sight_multiplier(&self) -> f32 {
+        self.weather.sight_multiplier()
+    }
+
+    /// Multiplier on the brightness and reach of real flames.
+    pub fn fire_multiplier(&self) -> f32 {
+        self.weather.fire_multiplier()
+    }
+
+    /// How much stronger than usual a yokai with `abilities` is in this
+    /// weather.
+    pub fn power(&self, abilities: &[Ability]) -> f32 {
+        abilities
+            .iter()
+            .map(|&ability| self.weather.ability_multiplier(ability))
+            .product()
+    }
+
+    /// What decides which yokai are abroad, and how many.
+    pub fn spawn_conditions(&self) -> SpawnConditions {
+        SpawnConditions::now(&self.clock, &self.weather)
+    }
+
+    /// Whether the snow is deep enough to leave footprints in.
+    pub fn leaves_tracks(&self) -> bool {
+        self.weather.intensity_of(WeatherKind::Snow) >= TRACK_SNOW
+    }
+
+    /// How hard a storm is raging, from 0 to 1.
+    pub fn storm(&self) -> f32 {
+        self.weather.intensity_of(WeatherKind::Storm)
+    }
+}
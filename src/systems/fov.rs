@@ -0,0 +1,112 @@
+use crate::{
+    components::*,
+    map::{fov::field_of_view, tile_map::TileMap},
+    systems::{AmbientTint, Environment, LightMap},
+    TerrainEntity,
+};
+use bevy::{prelude::*, utils::HashSet};
+
+/// How far the player sees in full daylight.
+const DAYLIGHT_SIGHT: f32 = 16.0;
+/// The player always sees at least this far, even on the darkest night.
+const MIN_SIGHT: i32 = 3;
+
+/// What the player can currently see, recomputed each turn.
+#[derive(Resource, Default)]
+pub struct FieldOfView {
+    /// Where the player is looking from.
+    pub origin: IVec2,
+    pub radius: i32,
+    pub visible: HashSet<IVec2>,
+}
+
+impl FieldOfView {
+    pub fn is_visible(&self, pos: IVec2) -> bool {
+        self.visible.contains(&pos)
+    }
+}
+
+/// Light from lanterns and fires at least this bright lets the player see a
+/// tile beyond their sight radius.
+const LIT: f32 = 0.1;
+
+/// Sight radius under the current light and weather: it shrinks as night
+/// falls, further still when the new moon leaves the night without
+/// moonlight, and in fog, rain and snow.
+fn sight_radius(tint: &AmbientTint, env: &Environment) -> i32 {
+    let sight = DAYLIGHT_SIGHT * tint.brightness() * env.sight_multiplier();
+    (sight.round() as i32).max(MIN_SIGHT)
+}
+
+/// Recompute the field of view when the player moves, the light or weather
+/// changes or the map around them does, and remember every tile that comes into view.
+/// Tiles lit by a light source can be seen from as far off as in daylight.
+pub fn update_fov(
+    tile_map: Option<ResMut<TileMap>>,
+    tint: Res<AmbientTint>,
+    env: Environment,
+    lights: Res<LightMap>,
+    mut fov: ResMut<FieldOfView>,
+    player_query: Query<Ref<GridPosition>, With<PlayerBody>>,
+) {
+    let Some(mut tile_map) = tile_map else {
+        return;
+    };
+    let Ok(player) = player_query.get_single() else {
+        return;
+    };
+    if !player.is_changed()
+        && !tint.is_changed()
+        && !env.weather_changed()
+        && !lights.is_changed()
+        && !tile_map.is_changed()
+    {
+        return;
+    }
+
+    // Exploring tiles changes the map, which would recompute the view next
+    // frame; only explore and mark the view changed when it really is
+    let origin: IVec2 = (*player).into();
+    let radius = sight_radius(&tint, &env);
+    let mut visible = field_of_view(origin, DAYLIGHT_SIGHT as i32, |pos| tile_map.opacity(pos));
+    visible.retain(|&pos| {
+        (pos - origin).length_squared() <= radius * radius || lights.at(pos).max_element() >= LIT
+    });
+    if fov.origin != origin || fov.radius != radius || fov.visible != visible {
+        for &pos in &visible {
+            tile_map.explore(pos);
+        }
+        *fov = FieldOfView {
+            origin,
+            radius,
+            visible,
+        };
+    }
+}
+
+/// Hide glyphs on the grid outside the field of view. Explored terrain stays
+/// shown, drawn from memory by the lighting; monsters and items out of
+/// sight are hidden, and unexplored terrain is left blank. The player is
+/// always shown.
+#[allow(clippy::type_complexity)]
+pub fn apply_fov(
+    fov: Res<FieldOfView>,
+    tile_map: Option<Res<TileMap>>,
+    mut query: Query<(Ref<GridPosition>, &mut Visibility, Has<TerrainEntity>), Without<Player>>,
+) {
+    let Some(tile_map) = tile_map else {
+        return;
+    };
+    for (position, mut visibility, terrain) in query.iter_mut() {
+        if !fov.is_changed() && !position.is_changed() {
+            continue;
+        }
+        let pos = (*position).into();
+        let shown = if fov.is_visible(pos) || (terrain && tile_map.is_explored(pos)) {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        visibility.set_if_neq(shown);
+    }
+}
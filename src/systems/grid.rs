@@ -0,0 +1,19 @@
+use crate::{components::GridPosition, map::tile_map::TileMap};
+use bevy::prelude::*;
+
+/// Derive each entity's `Transform` from its `GridPosition`, keeping its
+/// existing `z` so layering is unaffected.
+pub fn sync_grid_transforms(
+    tile_map: Option<Res<TileMap>>,
+    mut query: Query<(&GridPosition, &mut Transform), Changed<GridPosition>>,
+) {
+    let Some(tile_map) = tile_map else {
+        return;
+    };
+
+    for (grid_position, mut transform) in query.iter_mut() {
+        let world = tile_map.grid_to_world((*grid_position).into());
+        transform.translation.x = world.x;
+        transform.translation.y = world.y;
+    }
+}
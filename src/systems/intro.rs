@@ -1,4 +1,4 @@
-use crate::GameState;
+use crate::{systems::GameData, GameState};
 use bevy::{input::keyboard::KeyboardInput, prelude::*, text::FontSmoothing};
 use std::f32::consts::PI;
 
@@ -19,19 +19,10 @@ pub struct BackgroundText;
 #[derive(Component, Default)]
 pub struct IntroText;
 
-#[derive(Bundle, Default)]
-pub struct IntroTextBundle {
-    pub text: Text2d,
-    pub text_font: TextFont,
-    pub text_color: TextColor,
-    pub transform: Transform,
-    pub intro: IntroText,
-}
-
 pub fn setup_intro(mut commands: Commands, asset_server: Res<AssetServer>) {
     // Camera with explicit order
     commands.spawn((
-        Camera2d::default(),
+        Camera2d,
         Camera {
             order: 0,
             ..default()
@@ -43,7 +34,7 @@ pub fn setup_intro(mut commands: Commands, asset_server: Res<AssetServer>) {
     let font = asset_server.load("fonts/NotoSansJP-VariableFont_wght.ttf");
 
     // Spawn background decorative kanji
-    let background_kanji = vec!["神", "鬼", "龍", "虎", "蛇", "狐", "兎", "鳥"];
+    let background_kanji = ["神", "鬼", "龍", "虎", "蛇", "狐", "兎", "鳥"];
     for (i, kanji) in background_kanji.iter().enumerate() {
         let angle = (i as f32 / background_kanji.len() as f32) * 2.0 * PI;
         let radius = 200.0;
@@ -94,6 +85,7 @@ pub fn setup_intro(mut commands: Commands, asset_server: Res<AssetServer>) {
     ));
 }
 
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
 pub fn handle_intro(
     mut next_state: ResMut<NextState<GameState>>,
     mut keyboard_events: EventReader<KeyboardInput>,
@@ -101,6 +93,7 @@ pub fn handle_intro(
     intro_text: Query<Entity, With<IntroText>>,
     intro_camera: Query<Entity, With<IntroCamera>>,
     time: Res<Time>,
+    data: GameData,
     mut query_set: ParamSet<(
         Query<(&mut Transform, &mut TextColor), With<TitleText>>,
         Query<(&mut TextColor, &mut PressKeyText), With<PressKeyText>>,
@@ -126,12 +119,12 @@ pub fn handle_intro(
     for mut transform in query_set.p2().iter_mut() {
         transform.rotate_z(time.delta_secs() * 0.2);
     }
-    for _ in keyboard_events.read() {
+    // The map can't be generated until the terrain and bestiary have loaded
+    if keyboard_events.read().next().is_some() && data.is_loaded() {
         // Clean up intro text and camera
         for entity in intro_text.iter().chain(intro_camera.iter()) {
             commands.entity(entity).despawn();
         }
         next_state.set(GameState::Playing);
-        break;
     }
 }
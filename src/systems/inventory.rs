@@ -1,14 +1,10 @@
-use crate::{
-    components::*, create_text_color_bundle, InventoryState, InventoryUI, MainCamera, TerrainEntity,
-};
+use crate::{components::*, create_text_color_bundle, InventoryState, InventoryUI, MainCamera};
 use bevy::{prelude::*, window::PrimaryWindow};
 
 pub fn toggle_inventory(
     keyboard: Res<ButtonInput<KeyCode>>,
     mut query: Query<&mut PlayerStats, With<Player>>,
     mut commands: Commands,
-    _terrain_entities: Query<Entity, With<TerrainEntity>>,
-    _camera_query: Query<Entity, With<MainCamera>>,
     inventory_ui: Query<Entity, With<InventoryUI>>,
 ) {
     if keyboard.just_pressed(KeyCode::KeyI) {
@@ -21,7 +17,7 @@ pub fn toggle_inventory(
                 // Clean up inventory UI when toggling off
                 for entity in inventory_ui.iter() {
                     println!("Despawning inventory UI");
-                    commands.entity(entity).despawn();
+                    commands.entity(entity).despawn_recursive();
                 }
                 commands.remove_resource::<InventoryState>();
             }
@@ -34,6 +30,7 @@ pub fn render_inventory(
     asset_server: Res<AssetServer>,
     query: Query<&PlayerStats, With<Player>>,
     window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<Entity, With<MainCamera>>,
     mut inventory_state: ResMut<InventoryState>,
 ) {
     if !inventory_state.needs_update {
@@ -76,17 +73,23 @@ pub fn render_inventory(
                 stats.charisma
             );
 
-            commands.spawn((
-                create_text_color_bundle(
-                    font,
-                    &overlay,
-                    -window.width() / 2.0 + 150.0,
-                    window.height() / 2.0 - 100.0,
-                    0.0,
-                    Color::srgb(0.8, 0.8, 0.8),
-                ),
-                InventoryUI,
-            ));
+            // Parent the overlay to the camera so it stays fixed on screen
+            let Ok(camera) = camera_query.get_single() else {
+                return;
+            };
+            commands.entity(camera).with_children(|parent| {
+                parent.spawn((
+                    create_text_color_bundle(
+                        font,
+                        &overlay,
+                        -window.width() / 2.0 + 150.0,
+                        window.height() / 2.0 - 100.0,
+                        3.0,
+                        Color::srgb(0.8, 0.8, 0.8),
+                    ),
+                    InventoryUI,
+                ));
+            });
         }
     }
 }
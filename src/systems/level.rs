@@ -0,0 +1,206 @@
+use crate::{
+    components::*,
+    map::{
+        chunk::chunk_of,
+        generator::{level_seed, MapGenerator, MapGeneratorRegistry},
+        terrain::{Stairs, TerrainCatalog},
+        tile_map::TileMap,
+    },
+    seed::WorldSeed,
+    systems::{Strike, Track},
+    TerrainEntity,
+};
+use bevy::{prelude::*, utils::HashMap};
+use rand::{rngs::StdRng, SeedableRng};
+
+/// How far below the overworld the player is; 0 is the overworld itself.
+#[derive(Resource, Default)]
+pub struct CurrentLevel {
+    pub depth: u32,
+}
+
+/// Levels the player has left, kept so they look the same on return.
+#[derive(Resource, Default)]
+pub struct LevelStore {
+    levels: HashMap<u32, TileMap>,
+}
+
+/// Run a generator for a fresh level. Endless generators only fill the
+/// chunks around `center` and stream the rest in; finite ones generate the
+/// whole level at their default size.
+pub fn build_level(
+    generator: &dyn MapGenerator,
+    terrain: &TerrainCatalog,
+    seed: u64,
+    tile_size: f32,
+    center: IVec2,
+) -> TileMap {
+    let mut tile_map = TileMap::new(Vec2::ZERO, tile_size, None);
+    let mut endless = false;
+    for dy in -1..=1 {
+        for dx in -1..=1 {
+            let coord = chunk_of(center) + IVec2::new(dx, dy);
+            if let Some(grid) = generator.generate_chunk(terrain, seed, coord) {
+                tile_map.insert_grid_chunk(coord, grid);
+                endless = true;
+            }
+        }
+    }
+    if endless {
+        tile_map
+    } else {
+        let grid = generator.generate(terrain, seed, generator.default_size());
+        let mut rng = StdRng::seed_from_u64(seed);
+        TileMap::from_grid(&grid, terrain, tile_size, &mut rng)
+    }
+}
+
+/// Build the tile map for a level, restoring it if it was visited before.
+pub fn enter_level(
+    store: &mut LevelStore,
+    registry: &MapGeneratorRegistry,
+    terrain: &TerrainCatalog,
+    seed: WorldSeed,
+    depth: u32,
+    tile_size: f32,
+) -> TileMap {
+    store.levels.remove(&depth).unwrap_or_else(|| {
+        let generator = registry.generator_for_depth(depth);
+        build_level(
+            generator,
+            terrain,
+            level_seed(seed.0, depth),
+            tile_size,
+            IVec2::ZERO,
+        )
+    })
+}
+
+/// Move between levels when the player steps onto a staircase. The player
+/// arrives beside the matching staircase on the other level.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn use_stairs(
+    mut commands: Commands,
+    tile_map: Option<ResMut<TileMap>>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut store: ResMut<LevelStore>,
+    registry: Res<MapGeneratorRegistry>,
+    terrain: Res<TerrainCatalog>,
+    seed: Res<WorldSeed>,
+    mut player_query: Query<&mut GridPosition, (With<PlayerBody>, Changed<GridPosition>)>,
+    terrain_query: Query<Entity, With<TerrainEntity>>,
+    occupant_query: Query<Entity, Or<(With<Monster>, With<Item>, With<Track>, With<Strike>)>>,
+) {
+    let Some(mut tile_map) = tile_map else {
+        return;
+    };
+    let Ok(mut player_position) = player_query.get_single_mut() else {
+        return;
+    };
+
+    let depth = match tile_map
+        .map_item((*player_position).into())
+        .and_then(|item| item.stairs)
+    {
+        Some(Stairs::Down) => current_level.depth + 1,
+        Some(Stairs::Up) if current_level.depth > 0 => current_level.depth - 1,
+        _ => return,
+    };
+    let arrival_stairs = if depth > current_level.depth {
+        Stairs::Up
+    } else {
+        Stairs::Down
+    };
+
+    // Despawn the old level and put its tiles away for later
+    for entity in terrain_query.iter().chain(occupant_query.iter()) {
+        commands.entity(entity).despawn();
+    }
+    let next_map = enter_level(
+        &mut store,
+        &registry,
+        &terrain,
+        *seed,
+        depth,
+        tile_map.tile_size,
+    );
+    let mut previous_map = std::mem::replace(&mut *tile_map, next_map);
+    previous_map.clear_entities();
+    store.levels.insert(current_level.depth, previous_map);
+    current_level.depth = depth;
+
+    // Step off the stairs so arriving doesn't immediately trigger them again
+    let stairs = tile_map.find_stairs(arrival_stairs).unwrap_or_default();
+    let arrival = [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y]
+        .into_iter()
+        .map(|offset| stairs + offset)
+        .find(|&pos| {
+            tile_map
+                .map_item(pos)
+                .is_some_and(|item| !item.solid && item.stairs.is_none())
+        })
+        .unwrap_or(stairs);
+    *player_position = arrival.into();
+
+    // Chunks stream in around the new position, then monsters spawn in them
+    commands.spawn(SpawnMonstersMarker);
+}
+
+/// Debug command: F2 regenerates the current level with the next registered
+/// generator, keeping the player as close to where they stood as possible.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn cycle_level_generator(
+    mut commands: Commands,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    tile_map: Option<ResMut<TileMap>>,
+    current_level: Res<CurrentLevel>,
+    mut registry: ResMut<MapGeneratorRegistry>,
+    terrain: Res<TerrainCatalog>,
+    seed: Res<WorldSeed>,
+    mut player_query: Query<&mut GridPosition, With<PlayerBody>>,
+    terrain_query: Query<Entity, With<TerrainEntity>>,
+    occupant_query: Query<Entity, Or<(With<Monster>, With<Item>, With<Track>, With<Strike>)>>,
+) {
+    if !keyboard.just_pressed(KeyCode::F2) {
+        return;
+    }
+    let Some(mut tile_map) = tile_map else {
+        return;
+    };
+    let Ok(mut player_position) = player_query.get_single_mut() else {
+        return;
+    };
+
+    let depth = current_level.depth;
+    let names: Vec<String> = registry.names().map(String::from).collect();
+    let current = registry.generator_for_depth(depth).name();
+    let next_index = names
+        .iter()
+        .position(|name| name == current)
+        .map_or(0, |index| (index + 1) % names.len());
+    let Some(next) = names.get(next_index) else {
+        return;
+    };
+    registry.set_generator_for_depth(depth, next);
+    println!("Regenerating level {} with the {} generator", depth, next);
+
+    for entity in terrain_query.iter().chain(occupant_query.iter()) {
+        commands.entity(entity).despawn();
+    }
+    let center = IVec2::from(*player_position);
+    *tile_map = build_level(
+        registry.generator_for_depth(depth),
+        &terrain,
+        level_seed(seed.0, depth),
+        tile_map.tile_size,
+        center,
+    );
+
+    // Always mark the player as moved so the new level's chunks stream in
+    let arrival = tile_map
+        .nearest_walkable(center, 64)
+        .or_else(|| tile_map.walkable_positions().first().copied())
+        .unwrap_or(center);
+    *player_position = arrival.into();
+    commands.spawn(SpawnMonstersMarker);
+}
@@ -0,0 +1,265 @@
+use crate::{
+    components::{BaseColor, GridPosition, LightSource, MapItem, Player, PlayerBody},
+    map::{fov::field_of_view, tile_map::TileMap},
+    systems::{Environment, FieldOfView},
+    time::{
+        GameClock, Moonphases, Season, SUNRISE, SUNSET, TICKS_PER_DAY, TICKS_PER_HOUR,
+        WITCHING_HOUR,
+    },
+    weather::{Weather, WeatherKind},
+};
+use bevy::{prelude::*, utils::HashMap};
+
+/// Brightness of ambient light through the day, as `(time of day, level)`
+/// keyframes that are blended linearly, so dawn and dusk come on gradually.
+const DAYLIGHT: [(usize, f32); 10] = [
+    (0, 0.3),
+    (WITCHING_HOUR, 0.3),
+    (WITCHING_HOUR + TICKS_PER_HOUR / 2, 0.2),
+    (WITCHING_HOUR + TICKS_PER_HOUR, 0.3),
+    (SUNRISE, 0.3),
+    (SUNRISE + TICKS_PER_HOUR, 1.0),
+    (SUNSET - TICKS_PER_HOUR, 1.0),
+    (SUNSET, 0.5),
+    (SUNSET + TICKS_PER_HOUR, 0.3),
+    (TICKS_PER_DAY, 0.3),
+];
+
+/// Brightness of an ordinary night; moonlight is at full strength from here
+/// down.
+const NIGHT: f32 = 0.3;
+
+/// Multiplier applied to every world glyph's color, recomputed from the game
+/// clock.
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct AmbientTint(pub Vec3);
+
+impl Default for AmbientTint {
+    fn default() -> Self {
+        Self(Vec3::ONE)
+    }
+}
+
+impl AmbientTint {
+    /// Overall brightness, 1 at midday.
+    pub fn brightness(&self) -> f32 {
+        self.0.element_sum() / 3.0
+    }
+}
+
+fn daylight(time_of_day: usize) -> f32 {
+    DAYLIGHT
+        .windows(2)
+        .find(|pair| time_of_day < pair[1].0)
+        .map_or(NIGHT, |pair| {
+            let ((from, start), (to, end)) = (pair[0], pair[1]);
+            let t = (time_of_day - from) as f32 / (to - from) as f32;
+            start + (end - start) * t
+        })
+}
+
+fn season_tint(season: Season) -> Vec3 {
+    match season {
+        Season::Spring => Vec3::new(0.975, 1.0, 0.975),
+        Season::Summer => Vec3::new(1.0, 0.975, 0.975),
+        Season::Autumn => Vec3::new(1.0, 0.985, 0.957),
+        Season::Winter => Vec3::new(0.975, 0.975, 1.0),
+    }
+}
+
+fn moonlight(moon: Moonphases) -> f32 {
+    match moon {
+        Moonphases::New => 0.0,
+        Moonphases::WaxingCrescent | Moonphases::WaningCrescent => 0.025,
+        Moonphases::FirstQuarter | Moonphases::LastQuarter => 0.05,
+        Moonphases::WaxingGibbous | Moonphases::WaningGibbous => 0.075,
+        Moonphases::Full => 0.1,
+    }
+}
+
+/// Share of sun and moon light that each kind of weather blots out at full
+/// strength.
+fn cloud_cover(weather: &Weather) -> f32 {
+    let cover = weather.intensity_of(WeatherKind::Rain) * 0.2
+        + weather.intensity_of(WeatherKind::Snow) * 0.1
+        + weather.intensity_of(WeatherKind::Fog) * 0.15
+        + weather.intensity_of(WeatherKind::Storm) * 0.35;
+    cover.min(0.5)
+}
+
+/// The ambient tint at a moment: daylight colored by the season, plus
+/// moonlight that fades in as the sun goes down, both dimmed by cloud.
+pub fn ambient_tint(clock: &GameClock, weather: &Weather) -> Vec3 {
+    let level = daylight(clock.time_of_day());
+    let darkness = ((1.0 - level) / (1.0 - NIGHT)).clamp(0.0, 1.0);
+    (Vec3::splat(level) * season_tint(clock.season())
+        + Vec3::splat(moonlight(clock.moon_phase()) * darkness))
+        * (1.0 - cloud_cover(weather))
+}
+
+/// Light sources further than this from the player aren't cast, as the
+/// player couldn't see what they light.
+const LIGHT_RANGE: i32 = 32;
+
+/// Light cast by light sources onto each tile, added to the ambient light.
+#[derive(Resource, Default)]
+pub struct LightMap {
+    light: HashMap<IVec2, Vec3>,
+}
+
+impl LightMap {
+    /// Light reaching `pos` from every source, per sRGB channel.
+    pub fn at(&self, pos: IVec2) -> Vec3 {
+        self.light.get(&pos).copied().unwrap_or(Vec3::ZERO)
+    }
+}
+
+/// Spread each light source's light over the tiles it can reach, fading
+/// linearly to nothing just past its radius. Opaque tiles catch the light on
+/// their faces but cast shadows behind them, just as they block sight.
+fn cast_lights(tile_map: &TileMap, sources: &[(IVec2, LightSource)]) -> HashMap<IVec2, Vec3> {
+    let mut light = HashMap::default();
+    for &(origin, source) in sources {
+        let lit = field_of_view(origin, source.radius, |pos| tile_map.opacity(pos));
+        for pos in lit {
+            let distance = (pos - origin).as_vec2().length();
+            let falloff = 1.0 - distance / (source.radius + 1) as f32;
+            *light.entry(pos).or_insert(Vec3::ZERO) += source.color * falloff.max(0.0);
+        }
+    }
+    light
+}
+
+/// Recast light when a source moves, appears or goes out, the map changes
+/// under it or the weather shifts. Emissive terrain near the player shines
+/// along with light-carrying entities, and rain damps down real flames.
+pub fn update_lights(
+    tile_map: Option<Res<TileMap>>,
+    env: Environment,
+    mut lights: ResMut<LightMap>,
+    player_query: Query<&GridPosition, With<PlayerBody>>,
+    source_query: Query<(Ref<GridPosition>, Ref<LightSource>)>,
+    mut removed: RemovedComponents<LightSource>,
+) {
+    let Some(tile_map) = tile_map else {
+        return;
+    };
+    let Ok(&player) = player_query.get_single() else {
+        return;
+    };
+    let sources_changed = source_query
+        .iter()
+        .any(|(position, source)| position.is_changed() || source.is_changed());
+    let weather_changed = env.weather_changed();
+    if !sources_changed && removed.read().count() == 0 && !tile_map.is_changed() && !weather_changed
+    {
+        return;
+    }
+
+    let player: IVec2 = player.into();
+    let fire = env.fire_multiplier();
+    let mut sources: Vec<(IVec2, LightSource)> = source_query
+        .iter()
+        .map(|(position, source)| (IVec2::from(*position), dampened(*source, fire)))
+        .filter(|&(pos, _)| (pos - player).abs().max_element() <= LIGHT_RANGE)
+        .collect();
+    for y in -LIGHT_RANGE..=LIGHT_RANGE {
+        for x in -LIGHT_RANGE..=LIGHT_RANGE {
+            let pos = player + IVec2::new(x, y);
+            if let Some(glow) = tile_map.map_item(pos).and_then(|item| item.material.glow()) {
+                sources.push((pos, glow));
+            }
+        }
+    }
+    let light = cast_lights(&tile_map, &sources);
+    if lights.light != light {
+        lights.light = light;
+    }
+}
+
+/// A light source as the weather leaves it: rain shrinks flames, dimming
+/// them and cutting their reach, by the fire multiplier.
+fn dampened(source: LightSource, fire: f32) -> LightSource {
+    if !source.fire {
+        return source;
+    }
+    LightSource {
+        color: source.color * fire,
+        radius: ((source.radius as f32 * fire).round() as i32).max(1),
+        ..source
+    }
+}
+
+/// Recompute the ambient tint whenever time passes or the weather shifts.
+pub fn update_ambient(clock: Res<GameClock>, weather: Res<Weather>, mut tint: ResMut<AmbientTint>) {
+    if !clock.is_changed() && !weather.is_changed() {
+        return;
+    }
+    let next = AmbientTint(ambient_tint(&clock, &weather));
+    if *tint != next {
+        *tint = next;
+    }
+}
+
+/// Fog leaves tiles this close to the player untouched.
+const FOG_CLEAR: f32 = 2.0;
+/// Tiles of fog beyond `FOG_CLEAR` it takes to hide a tile completely.
+const FOG_DEPTH: f32 = 8.0;
+/// The grey that fog fades distant tiles into, before ambient light.
+const FOG_COLOR: Vec3 = Vec3::new(0.55, 0.55, 0.6);
+
+/// How far fog has faded a tile `distance` tiles from the player, from 0
+/// (clear) to 1 (lost in the fog).
+fn fog_fade(weather: &Weather, distance: f32) -> f32 {
+    let fog = weather.intensity_of(WeatherKind::Fog);
+    fog * ((distance - FOG_CLEAR) / FOG_DEPTH).clamp(0.0, 1.0)
+}
+
+/// How a remembered tile out of sight is drawn: dim and washed out to grey,
+/// with a cool cast, whatever the light.
+fn remembered(base: Srgba) -> Color {
+    let luminance = 0.3 * base.red + 0.59 * base.green + 0.11 * base.blue;
+    let grey = Vec3::new(luminance, luminance, luminance * 1.15);
+    let color = Vec3::new(base.red, base.green, base.blue).lerp(grey, 0.8) * 0.35;
+    Color::srgba(color.x, color.y, color.z, base.alpha)
+}
+
+/// Color world glyphs: tinted by the ambient light plus whatever light from
+/// light sources their surface reflects while in view, faded into any fog
+/// with distance, and in memory colors otherwise. Glyphs are only revisited
+/// when the light, the weather or the view changes, or when they are
+/// spawned, recolored or moved.
+#[allow(clippy::type_complexity)]
+pub fn apply_ambient(
+    tint: Res<AmbientTint>,
+    lights: Res<LightMap>,
+    fov: Res<FieldOfView>,
+    weather: Res<Weather>,
+    mut query: Query<(
+        Ref<BaseColor>,
+        Ref<GridPosition>,
+        &mut TextColor,
+        Option<&MapItem>,
+        Has<Player>,
+    )>,
+) {
+    let refresh_all =
+        tint.is_changed() || lights.is_changed() || fov.is_changed() || weather.is_changed();
+    for (base, position, mut color, map_item, player) in query.iter_mut() {
+        if !refresh_all && !base.is_changed() && !position.is_changed() {
+            continue;
+        }
+        let pos = (*position).into();
+        let base = base.0.to_srgba();
+        color.0 = if player || fov.is_visible(pos) {
+            let reflectance = map_item.map_or(1.0, |item| item.material.reflectance);
+            let light = (tint.0 + lights.at(pos) * reflectance).min(Vec3::ONE);
+            let lit = Vec3::new(base.red, base.green, base.blue) * light;
+            let distance = (pos - fov.origin).as_vec2().length();
+            let color = lit.lerp(FOG_COLOR * tint.0, fog_fade(&weather, distance));
+            Color::srgba(color.x, color.y, color.z, base.alpha)
+        } else {
+            remembered(base)
+        };
+    }
+}
@@ -1,13 +1,37 @@
+mod audio;
+mod camera;
+mod chunks;
+mod clock;
 mod combat;
+mod data;
+mod environment;
+mod fov;
+mod grid;
+mod intro;
 mod inventory;
+mod level;
+mod lighting;
 mod player;
+mod seasons;
 mod setup;
-mod intro;
-mod audio;
+mod turns;
+mod weather;
 
+pub use audio::*;
+pub use camera::*;
+pub use chunks::*;
+pub use clock::*;
 pub use combat::*;
+pub use data::*;
+pub use environment::*;
+pub use fov::*;
+pub use grid::*;
+pub use intro::*;
 pub use inventory::*;
+pub use level::*;
+pub use lighting::*;
 pub use player::*;
+pub use seasons::*;
 pub use setup::*;
-pub use intro::*;
-pub use audio::*;
+pub use turns::*;
+pub use weather::*;
@@ -1,113 +1,150 @@
-use crate::{components::*, TerrainEntity};
+use crate::{components::*, map::tile_map::TileMap, systems::Environment, time::PlayerActed};
 use bevy::prelude::*;
 
+/// Damage the player deals a yokai in ordinary weather.
+const PLAYER_DAMAGE: f32 = 5.0;
+
+/// Take the player's turn from the keyboard once the scheduler has brought it
+/// round: WASD moves or attacks, space waits and E uses the item underfoot.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 pub fn player_movement(
+    mut commands: Commands,
     keyboard: Res<ButtonInput<KeyCode>>,
-    mut param_set: ParamSet<(
-        Query<&mut Transform, With<Player>>,
-        Query<(&Transform, &mut Monster)>,
-        Query<(&Transform, &MapItem), With<TerrainEntity>>,
-    )>,
+    tile_map: Option<Res<TileMap>>,
+    mut body_query: Query<(&mut GridPosition, &Actor), (With<PlayerBody>, Without<Monster>)>,
+    mut monster_query: Query<(&GridPosition, &mut Monster)>,
+    item_query: Query<(Entity, &GridPosition, &Item, Has<LightSource>), Without<PlayerBody>>,
     mut message_query: Query<(&mut Text2d, &mut CombatMessage)>,
+    mut actions: EventWriter<PlayerActed>,
+    env: Environment,
 ) {
-    // First check if player exists in the world
-    if param_set.p0().is_empty() {
+    // First check if the player and the map exist in the world
+    let Some(tile_map) = tile_map else {
+        return;
+    };
+    let Ok((mut player_position, actor)) = body_query.get_single_mut() else {
         return; // Player not loaded yet, don't process movement
+    };
+    if !actor.is_ready() {
+        return;
+    }
+
+    if keyboard.just_pressed(KeyCode::Space) {
+        actions.send(PlayerActed {
+            action: Action::Wait,
+        });
+        return;
+    }
+    if keyboard.just_pressed(KeyCode::KeyE) {
+        let item = item_query
+            .iter()
+            .find(|(_, position, _, _)| **position == *player_position);
+        let notice = match item {
+            Some((_, _, item, true)) => format!("The {} is fixed in place.", item.name),
+            Some((entity, _, item, false)) => {
+                commands.entity(entity).despawn();
+                actions.send(PlayerActed {
+                    action: Action::UseItem,
+                });
+                format!("You use the {}.", item.name)
+            }
+            None => String::from("There is nothing here to use."),
+        };
+        if let Ok((mut text, mut message)) = message_query.get_single_mut() {
+            message.message = notice;
+            text.0 = message.message.clone();
+        }
+        return;
     }
 
-    let grid_size = 12.0;
-    let mut delta = Vec2::ZERO;
+    let mut delta = IVec2::ZERO;
 
     if keyboard.just_pressed(KeyCode::KeyW) {
-        delta.y += grid_size;
+        delta.y -= 1;
     }
     if keyboard.just_pressed(KeyCode::KeyS) {
-        delta.y -= grid_size;
+        delta.y += 1;
     }
     if keyboard.just_pressed(KeyCode::KeyA) {
-        delta.x -= grid_size;
+        delta.x -= 1;
     }
     if keyboard.just_pressed(KeyCode::KeyD) {
-        delta.x += grid_size;
+        delta.x += 1;
     }
 
-    if delta != Vec2::ZERO {
-        let player_pos = param_set.p0().iter().next().unwrap().translation.clone();
-        let new_pos = Vec3::new(player_pos.x + delta.x, player_pos.y + delta.y, player_pos.z);
+    if delta == IVec2::ZERO {
+        return;
+    }
 
-        // Check for monster collision
-        let mut collided = false;
-        for (monster_transform, mut monster) in param_set.p1().iter_mut() {
-            if (monster_transform.translation.x - new_pos.x).abs() < 1.0
-                && (monster_transform.translation.y - new_pos.y).abs() < 1.0
-                && monster.is_alive
-            {
-                collided = true;
-                // Combat logic
-                monster.hp = monster.hp.saturating_sub(5); // Player deals 5 damage
+    let new_position = player_position.offset(delta);
 
-                if monster.hp == 0 {
-                    monster.is_alive = false;
-                    if let Ok((mut text, mut message)) = message_query.get_single_mut() {
-                        message.message = format!("You defeated the {}!", monster.name);
-                        text.0 = message.message.clone();
-                    }
-                } else {
-                    if let Ok((mut text, mut message)) = message_query.get_single_mut() {
-                        message.message =
-                            format!("You hit the {}! ({} HP left)", monster.name, monster.hp);
-                        text.0 = message.message.clone();
-                    }
-                }
-                break;
-            }
-        }
+    // Check for monster collision
+    let target = monster_query
+        .iter_mut()
+        .find(|(position, monster)| **position == new_position && monster.is_alive);
 
-        if !collided {
-            // Get window dimensions from first terrain entity's position
-            let mut min_x = f32::MAX;
-            let mut max_x = f32::MIN;
-            let mut min_y = f32::MAX;
-            let mut max_y = f32::MIN;
-            
-            for (transform, _) in param_set.p2().iter() {
-                min_x = min_x.min(transform.translation.x);
-                max_x = max_x.max(transform.translation.x);
-                min_y = min_y.min(transform.translation.y);
-                max_y = max_y.max(transform.translation.y);
-            }
+    if let Some((_, mut monster)) = target {
+        actions.send(PlayerActed {
+            action: Action::Attack,
+        });
 
-            // Check if new position would be off the map
-            if new_pos.x < min_x || new_pos.x > max_x || 
-               new_pos.y < min_y || new_pos.y > max_y {
-                return;
-            }
+        // Combat logic; yokai the weather empowers shrug off more of the blow
+        let damage = (PLAYER_DAMAGE / env.power(&monster.abilities))
+            .round()
+            .max(1.0);
+        monster.hp = monster.hp.saturating_sub(damage as u32);
 
-            // Check for solid terrain at the new position
-            let mut solid_terrain = false;
-            for (terrain_transform, map_item) in param_set.p2().iter() {
-                if (terrain_transform.translation.x - new_pos.x).abs() < 1.0
-                    && (terrain_transform.translation.y - new_pos.y).abs() < 1.0
-                {
-                    if map_item.solid {
-                        solid_terrain = true;
-                        break;
-                    }
-                }
+        if monster.hp == 0 {
+            monster.is_alive = false;
+            if let Ok((mut text, mut message)) = message_query.get_single_mut() {
+                message.message = format!("You defeated the {}!", monster.name);
+                text.0 = message.message.clone();
             }
+        } else if let Ok((mut text, mut message)) = message_query.get_single_mut() {
+            message.message = format!("You hit the {}! ({} HP left)", monster.name, monster.hp);
+            text.0 = message.message.clone();
+        }
+        return;
+    }
 
-            if !solid_terrain {
-                // Move both player body and weapon
-                for mut transform in param_set.p0().iter_mut() {
-                    if transform.translation.x == player_pos.x {
-                        // This is the body
-                        transform.translation = new_pos;
-                    } else {
-                        // This is the weapon
-                        transform.translation = Vec3::new(new_pos.x + 12.0, new_pos.y, new_pos.z);
-                    }
-                }
+    // Solid terrain and the edge of the map both block movement
+    if tile_map.is_walkable(new_position.into()) {
+        let entering_water =
+            tile_map.is_water(new_position.into()) && !tile_map.is_water((*player_position).into());
+        let cost = env.movement_cost(&tile_map, new_position.into());
+        *player_position = new_position;
+        actions.send(PlayerActed {
+            action: Action::Move { cost },
+        });
+
+        let item = item_query
+            .iter()
+            .find(|(_, position, _, _)| **position == new_position)
+            .map(|(_, _, item, _)| item);
+        let notice = match item {
+            Some(item) => Some(format!("You see a {} here.", item.name)),
+            None if entering_water => Some(String::from("You wade into the water.")),
+            None => None,
+        };
+        if let Some(notice) = notice {
+            if let Ok((mut text, mut message)) = message_query.get_single_mut() {
+                message.message = notice;
+                text.0 = message.message.clone();
             }
         }
     }
 }
+
+/// Keep the weapon glyph one tile to the right of the player's body.
+pub fn follow_player_weapon(
+    body_query: Query<&GridPosition, (With<PlayerBody>, Changed<GridPosition>)>,
+    mut weapon_query: Query<&mut GridPosition, (With<PlayerWeapon>, Without<PlayerBody>)>,
+) {
+    let Ok(body_position) = body_query.get_single() else {
+        return;
+    };
+
+    for mut weapon_position in weapon_query.iter_mut() {
+        *weapon_position = body_position.offset(IVec2::X);
+    }
+}
@@ -0,0 +1,67 @@
+use crate::{
+    components::BaseColor,
+    map::{
+        chunk::chunk_positions, seasons::SeasonFront, terrain::TerrainCatalog, tile_map::TileMap,
+    },
+    seed::WorldSeed,
+    time::{GameClock, Season, TICKS_PER_HOUR},
+    TerrainEntity,
+};
+use bevy::prelude::*;
+
+/// Re-skin the terrain for the season every game hour, and whenever chunks
+/// are spawned, so the change spreads across the map as the days go by.
+/// Tiles only change glyph and color here; the lighting tints the new color
+/// like any other.
+#[allow(clippy::type_complexity)]
+pub fn reskin_terrain(
+    clock: Res<GameClock>,
+    seed: Res<WorldSeed>,
+    terrain: Option<Res<TerrainCatalog>>,
+    tile_map: Option<ResMut<TileMap>>,
+    mut last: Local<(usize, Vec<(IVec2, Option<Entity>)>)>,
+    mut glyph_query: Query<(&mut Text2d, &mut BaseColor), With<TerrainEntity>>,
+) {
+    let (Some(terrain), Some(mut tile_map)) = (terrain, tile_map) else {
+        return;
+    };
+
+    // Chunks are told apart by their first tile's entity as well as their
+    // coordinates, so respawned chunks and new levels are dressed too
+    let hour = clock.tick / TICKS_PER_HOUR;
+    let mut chunks: Vec<(IVec2, Option<Entity>)> = tile_map
+        .spawned_chunks()
+        .map(|coord| (coord, chunk_positions(coord).next()))
+        .map(|(coord, first)| (coord, first.and_then(|pos| tile_map.entity(pos))))
+        .collect();
+    chunks.sort_by_key(|(coord, _)| (coord.x, coord.y));
+    if last.0 == hour && last.1 == chunks && !terrain.is_changed() {
+        return;
+    }
+
+    let front = SeasonFront::new(seed.0);
+    let changes: Vec<(IVec2, Option<Season>)> = chunks
+        .iter()
+        .flat_map(|&(coord, _)| chunk_positions(coord))
+        .filter_map(|pos| {
+            let item = tile_map.map_item(pos)?;
+            let season = front.season_at(&clock, pos, terrain.get(&item.terrain));
+            (item.season != season).then_some((pos, season))
+        })
+        .collect();
+    *last = (hour, chunks);
+
+    for (pos, season) in changes {
+        let Some(item) = tile_map.dress(pos, &terrain, season) else {
+            continue;
+        };
+        let (glyph, color) = (item.current_character().to_string(), item.current_color());
+        let Some(entity) = tile_map.entity(pos) else {
+            continue;
+        };
+        if let Ok((mut text, mut base)) = glyph_query.get_mut(entity) {
+            text.0 = glyph;
+            base.0 = color;
+        }
+    }
+}
@@ -1,191 +1,373 @@
-use crate::{components::*, create_text_color_bundle, TerrainEntity};
-use bevy::{prelude::*, window::PrimaryWindow};
-
-use rand::seq::SliceRandom;
-
-fn find_valid_spawn_position(
-    window: &Window,
-    char_size: f32,
-    terrain_query: &Query<(&Transform, &MapItem), With<TerrainEntity>>,
-) -> Option<(f32, f32)> {
-    let start_x = -window.width() / 2.0 + char_size / 2.0;
-    let start_y = window.height() / 2.0 - char_size / 2.0;
-    let mut valid_positions = Vec::new();
-
-    // Collect all valid positions
-    for row in 0..((window.height() / char_size) as i32) {
-        for col in 0..((window.width() / char_size) as i32) {
-            let x = start_x + col as f32 * char_size;
-            let y = start_y - row as f32 * char_size;
-            
-            let mut is_valid = true;
-            for (transform, map_item) in terrain_query.iter() {
-                if (transform.translation.x - x).abs() < 1.0 && 
-                   (transform.translation.y - y).abs() < 1.0 {
-                    if map_item.solid {
-                        is_valid = false;
-                        break;
-                    }
-                }
-            }
-            
-            if is_valid {
-                valid_positions.push((x, y));
-            }
+use crate::{
+    bestiary::{Bestiary, SpawnConditions, Yokai},
+    components::*,
+    create_text_color_bundle,
+    map::{map_file::Placement, tile_map::TileMap},
+    seed::WorldRng,
+    systems::Environment,
+    time::TICKS_PER_HOUR,
+};
+use bevy::{prelude::*, utils::HashSet};
+
+use rand::{seq::SliceRandom, Rng};
+
+/// Yokai roaming a level at once under a quiet moon and a clear sky.
+const BASE_POPULATION: f32 = 8.0;
+/// Ticks between checks on which yokai are abroad.
+const RESPAWN_INTERVAL: usize = TICKS_PER_HOUR;
+/// Yokai this close to the player neither appear nor slip away, so the
+/// population only changes out of sight.
+const QUIET_RADIUS: i32 = 8;
+/// Tiles tried for each yokai before giving up on finding it a home.
+const SPAWN_ATTEMPTS: usize = 20;
+
+/// How many yokai should be abroad under `conditions`.
+fn population_target(conditions: &SpawnConditions) -> usize {
+    (BASE_POPULATION * conditions.activity()).round() as usize
+}
+
+/// Spawn up to `count` yokai on free tiles among `positions`. Each picks a
+/// tile uniformly, then a yokai from the spawn table of the tile's biome, so
+/// tiles whose biome nothing haunts right now stay empty. Returns how many
+/// were spawned.
+#[allow(clippy::too_many_arguments)]
+fn populate(
+    commands: &mut Commands,
+    tile_map: &TileMap,
+    font: &Handle<Font>,
+    bestiary: &Bestiary,
+    conditions: &SpawnConditions,
+    positions: &[IVec2],
+    occupied: &mut HashSet<IVec2>,
+    count: usize,
+    rng: &mut impl Rng,
+) -> usize {
+    let mut spawned = 0;
+    for _ in 0..count * SPAWN_ATTEMPTS {
+        if spawned == count {
+            break;
         }
+        let Some(&pos) = positions.choose(rng) else {
+            break;
+        };
+        let Some(biome) = tile_map.biome(pos).filter(|_| !occupied.contains(&pos)) else {
+            continue;
+        };
+        let table = bestiary.spawn_table(biome, conditions);
+        let Ok(&(yokai, _)) = table.choose_weighted(rng, |&(_, weight)| weight) else {
+            continue;
+        };
+        spawn_monster(commands, tile_map, font, yokai, pos, rng);
+        occupied.insert(pos);
+        spawned += 1;
     }
+    spawned
+}
+
+/// Whether `pos` is too far from the player for them to see yokai come and go.
+fn out_of_sight(player: IVec2, pos: IVec2) -> bool {
+    (pos - player).abs().max_element() > QUIET_RADIUS
+}
+
+fn find_valid_spawn_position(tile_map: &TileMap, rng: &mut WorldRng) -> Option<IVec2> {
+    // Only the largest connected area, so the player never starts sealed in,
+    // and off the stairs, so the first step doesn't take them down a level
+    let valid_positions: Vec<IVec2> = tile_map
+        .largest_region()
+        .into_iter()
+        .filter(|&pos| {
+            tile_map
+                .map_item(pos)
+                .is_some_and(|item| item.stairs.is_none())
+        })
+        .collect();
 
     // Randomly select one of the valid positions
-    valid_positions.choose(&mut rand::thread_rng()).copied()
+    valid_positions.choose(&mut rng.0).copied()
 }
 
+/// Spawn a monster from its bestiary entry at a grid position, drawn with a
+/// random one of its glyph variants.
+fn spawn_monster(
+    commands: &mut Commands,
+    tile_map: &TileMap,
+    font: &Handle<Font>,
+    yokai: &Yokai,
+    pos: IVec2,
+    rng: &mut impl Rng,
+) -> Entity {
+    let variant = rng.gen_range(0..yokai.variant_count());
+    let world = tile_map.grid_to_world(pos);
+    let mut monster = commands.spawn((
+        create_text_color_bundle(
+            font.clone(),
+            yokai.glyph(variant),
+            world.x,
+            world.y,
+            1.0,
+            yokai.color(variant),
+        ),
+        BaseColor(yokai.color(variant)),
+        Monster {
+            hp: yokai.hp,
+            max_hp: yokai.hp,
+            strength: yokai.strength,
+            name: yokai.name.en.clone(),
+            is_alive: true,
+            abilities: yokai.abilities.clone(),
+        },
+        Actor::new(yokai.speed),
+        GridPosition::from(pos),
+    ));
+    if let Some(light) = yokai.light {
+        monster.insert(light.source());
+    }
+    monster.id()
+}
+
+/// Populate a freshly entered level with the yokai abroad at this hour.
+#[allow(clippy::too_many_arguments)]
 pub fn spawn_monsters(
     mut commands: Commands,
     marker_query: Query<Entity, With<SpawnMonstersMarker>>,
-    terrain_query: Query<(&Transform, &MapItem), With<TerrainEntity>>,
+    tile_map: Option<Res<TileMap>>,
+    bestiary: Res<Bestiary>,
+    env: Environment,
+    mut world_rng: ResMut<WorldRng>,
     asset_server: Res<AssetServer>,
+    player_query: Query<&GridPosition, With<PlayerBody>>,
+    monster_query: Query<&GridPosition, With<Monster>>,
 ) {
-    // Only run if we have the marker
+    // Only run if we have the marker and the terrain is ready
     if marker_query.is_empty() {
         return;
     }
+    let Some(tile_map) = tile_map else {
+        return;
+    };
 
     let font = asset_server.load("fonts/NotoSansJP-VariableFont_wght.ttf");
-    let mut valid_positions = Vec::new();
 
-    // Collect all non-solid positions
-    for (transform, map_item) in terrain_query.iter() {
-        if !map_item.solid {
-            valid_positions.push((transform.translation.x, transform.translation.y));
+    // Collect the positions the player can walk to, away from the player
+    let mut occupied: HashSet<IVec2> = monster_query.iter().map(|&pos| pos.into()).collect();
+    let valid_positions: Vec<IVec2> = match player_query.get_single() {
+        Ok(&player) => {
+            occupied.insert(player.into());
+            tile_map
+                .reachable_from(player.into())
+                .into_iter()
+                .filter(|&pos| out_of_sight(player.into(), pos))
+                .collect()
         }
+        Err(_) => tile_map.largest_region(),
+    };
+
+    // Spawn monsters at valid positions from their biomes' spawn tables
+    let conditions = env.spawn_conditions();
+    let count = population_target(&conditions).saturating_sub(monster_query.iter().len());
+    populate(
+        &mut commands,
+        &tile_map,
+        &font,
+        &bestiary,
+        &conditions,
+        &valid_positions,
+        &mut occupied,
+        count,
+        &mut world_rng.0,
+    );
+
+    // Clean up the marker
+    for entity in marker_query.iter() {
+        commands.entity(entity).despawn();
     }
+}
 
-    // Spawn monsters at random valid positions
-    if !valid_positions.is_empty() {
-        use rand::seq::SliceRandom;
-        let mut rng = rand::thread_rng();
-
-        // Oni
-        if let Some(pos) = valid_positions.choose(&mut rng) {
-            commands.spawn((
-                create_text_color_bundle(
-                    font.clone(),
-                    "鬼",
-                    pos.0,
-                    pos.1,
-                    1.0,
-                    Color::srgb(1.0, 0.0, 0.0),
-                ),
-                Monster {
-                    hp: 20,
-                    max_hp: 20,
-                    strength: 5,
-                    name: String::from("Oni"),
-                    is_alive: true,
-                },
-            ));
-        }
+/// Every game hour, let yokai whose time has passed slip away out of the
+/// player's sight and top the population back up with those now abroad, so
+/// it follows the time of day, the moon, the season and the weather.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn respawn_monsters(
+    mut commands: Commands,
+    env: Environment,
+    mut last_check: Local<Option<usize>>,
+    tile_map: Option<Res<TileMap>>,
+    bestiary: Option<Res<Bestiary>>,
+    mut world_rng: ResMut<WorldRng>,
+    asset_server: Res<AssetServer>,
+    player_query: Query<&GridPosition, With<PlayerBody>>,
+    monster_query: Query<(Entity, &GridPosition, &Monster)>,
+) {
+    // Several ticks can pass in a frame, so compare intervals, not ticks
+    let interval = env.clock().tick / RESPAWN_INTERVAL;
+    if *last_check == Some(interval) {
+        return;
+    }
+    *last_check = Some(interval);
+    let (Some(tile_map), Some(bestiary)) = (tile_map, bestiary) else {
+        return;
+    };
+    let Ok(&player) = player_query.get_single() else {
+        return;
+    };
+    let player: IVec2 = player.into();
+    let conditions = env.spawn_conditions();
 
-        // Goblin
-        if let Some(pos) = valid_positions.choose(&mut rng) {
-            commands.spawn((
-                create_text_color_bundle(
-                    font.clone(),
-                    "G",
-                    pos.0,
-                    pos.1,
-                    1.0,
-                    Color::srgb(0.0, 1.0, 0.0),
-                ),
-                Monster {
-                    hp: 10,
-                    max_hp: 10,
-                    strength: 3,
-                    name: String::from("Goblin"),
-                    is_alive: true,
-                },
-            ));
+    let mut occupied = HashSet::from([player]);
+    let mut remaining = 0;
+    for (entity, &position, monster) in monster_query.iter() {
+        let pos: IVec2 = position.into();
+        let abroad = bestiary
+            .get(&monster.name)
+            .is_none_or(|yokai| yokai.activity(&conditions) > 0.0);
+        if !monster.is_alive {
+            continue;
+        } else if abroad || !out_of_sight(player, pos) {
+            occupied.insert(pos);
+            remaining += 1;
+        } else {
+            commands.entity(entity).despawn();
         }
+    }
 
-        // Kappa
-        if let Some(pos) = valid_positions.choose(&mut rng) {
-            commands.spawn((
-                create_text_color_bundle(
-                    font.clone(),
-                    "河",
-                    pos.0,
-                    pos.1,
-                    1.0,
-                    Color::srgb(0.0, 0.0, 1.0),
-                ),
-                Monster {
-                    hp: 15,
-                    max_hp: 15,
-                    strength: 4,
-                    name: String::from("Kappa"),
-                    is_alive: true,
-                },
-            ));
-        }
+    let count = population_target(&conditions).saturating_sub(remaining);
+    if count == 0 {
+        return;
     }
+    let valid_positions: Vec<IVec2> = tile_map
+        .reachable_from(player)
+        .into_iter()
+        .filter(|&pos| out_of_sight(player, pos))
+        .collect();
+    let font = asset_server.load("fonts/NotoSansJP-VariableFont_wght.ttf");
+    populate(
+        &mut commands,
+        &tile_map,
+        &font,
+        &bestiary,
+        &conditions,
+        &valid_positions,
+        &mut occupied,
+        count,
+        &mut world_rng.0,
+    );
+}
 
-    // Clean up the marker
-    for entity in marker_query.iter() {
-        commands.entity(entity).despawn();
+/// Spawn the monsters and items that map files placed, once the chunk under
+/// each has been spawned.
+pub fn spawn_placements(
+    mut commands: Commands,
+    tile_map: Option<ResMut<TileMap>>,
+    bestiary: Res<Bestiary>,
+    mut world_rng: ResMut<WorldRng>,
+    asset_server: Res<AssetServer>,
+) {
+    let Some(mut tile_map) = tile_map else {
+        return;
+    };
+    // Only borrow the map mutably when there is something to take, so it
+    // isn't marked changed every frame for the systems that watch it
+    if !tile_map.has_spawned_placements() {
+        return;
+    }
+    let placements = tile_map.take_spawned_placements();
+
+    let font = asset_server.load("fonts/NotoSansJP-VariableFont_wght.ttf");
+    for (pos, placement) in placements {
+        match placement {
+            Placement::Monster(name) => match bestiary.get(&name) {
+                Some(yokai) => {
+                    spawn_monster(
+                        &mut commands,
+                        &tile_map,
+                        &font,
+                        yokai,
+                        pos,
+                        &mut world_rng.0,
+                    );
+                }
+                None => println!("Map file places unknown monster {}", name),
+            },
+            Placement::Item { name, glyph } => {
+                let world = tile_map.grid_to_world(pos);
+                let color = Color::srgb(0.9, 0.75, 0.3);
+                commands.spawn((
+                    create_text_color_bundle(
+                        font.clone(),
+                        &glyph.to_string(),
+                        world.x,
+                        world.y,
+                        0.5,
+                        color,
+                    ),
+                    BaseColor(color),
+                    Item { name },
+                    GridPosition::from(pos),
+                ));
+            }
+            Placement::Light { kind, glyph } => {
+                let world = tile_map.grid_to_world(pos);
+                let source = kind.source();
+                let color = Color::srgb(source.color.x, source.color.y, source.color.z);
+                commands.spawn((
+                    create_text_color_bundle(
+                        font.clone(),
+                        &glyph.to_string(),
+                        world.x,
+                        world.y,
+                        0.5,
+                        color,
+                    ),
+                    BaseColor(color),
+                    Item {
+                        name: kind.name().to_string(),
+                    },
+                    source,
+                    GridPosition::from(pos),
+                ));
+            }
+        }
     }
 }
 
 pub fn spawn_player(
     mut commands: Commands,
-    window_query: Query<&Window, With<PrimaryWindow>>,
-    terrain_query: Query<(&Transform, &MapItem), With<TerrainEntity>>,
+    tile_map: Res<TileMap>,
+    mut world_rng: ResMut<WorldRng>,
     asset_server: Res<AssetServer>,
 ) {
-    let window = window_query.single();
-    let char_size = 12.0;
-
-    // Find a valid spawn position
-    let (start_x, start_y) = match find_valid_spawn_position(window, char_size, &terrain_query) {
-        Some(pos) => pos,
-        None => {
-            // Fallback to default position if no valid position found
-            let x = -window.width() / 2.0 + char_size / 2.0;
-            let y = window.height() / 2.0 - char_size / 2.0;
-            (x, y)
-        }
-    };
+    // Find a valid spawn position, falling back to the top-left tile
+    let spawn = find_valid_spawn_position(&tile_map, &mut world_rng).unwrap_or(IVec2::ZERO);
+    let start = tile_map.grid_to_world(spawn);
+    let char_size = tile_map.tile_size;
 
     // Load the font
     let font = asset_server.load("fonts/NotoSansJP-VariableFont_wght.ttf");
+    let color = Color::srgb(0.8, 0.8, 0.8);
 
     // Spawn player body
     commands.spawn((
-        create_text_color_bundle(
-            font.clone(),
-            "@",
-            start_x,
-            start_y,
-            1.0,
-            Color::srgb(0.8, 0.8, 0.8),
-        ),
+        create_text_color_bundle(font.clone(), "@", start.x, start.y, 1.0, color),
+        BaseColor(color),
         Visibility::default(),
         Player,
         PlayerBody,
         PlayerStats::default(),
+        Actor {
+            speed: TURN_ENERGY,
+            energy: TURN_ENERGY,
+        },
+        LightKind::Lantern.source(),
+        GridPosition::from(spawn),
     ));
 
     // Spawn player weapon
     commands.spawn((
-        create_text_color_bundle(
-            font.clone(),
-            "/",
-            start_x + char_size,
-            start_y,
-            1.0,
-            Color::srgb(0.8, 0.8, 0.8),
-        ),
+        create_text_color_bundle(font.clone(), "/", start.x + char_size, start.y, 1.0, color),
+        BaseColor(color),
         Visibility::default(),
         Player,
         PlayerWeapon,
+        GridPosition::from(spawn + IVec2::X),
     ));
 }
@@ -0,0 +1,269 @@
+use crate::{
+    components::*,
+    map::tile_map::TileMap,
+    seed::WorldRng,
+    systems::{Environment, FieldOfView},
+    time::{PlayerActed, TimePassed},
+};
+use bevy::{prelude::*, utils::HashSet};
+use rand::{seq::SliceRandom, Rng};
+
+/// Most ticks the world runs on in one frame waiting for the player.
+const MAX_TICKS: usize = 1000;
+/// Yokai that can see the player come after them from this many tiles away.
+const CHASE_RADIUS: i32 = 10;
+/// Chance a yokai with nothing to chase wanders a step rather than waiting.
+const WANDER_CHANCE: f64 = 0.5;
+
+const STEPS: [IVec2; 4] = [IVec2::NEG_Y, IVec2::Y, IVec2::NEG_X, IVec2::X];
+
+/// Decide and carry out one yokai's turn: attack the player if next to them,
+/// close in if the player can be seen, otherwise wander. Returns what the
+/// yokai did, and anything the player should be told.
+#[allow(clippy::too_many_arguments)]
+fn monster_turn(
+    position: &mut GridPosition,
+    monster: &Monster,
+    player: IVec2,
+    stats: &mut PlayerStats,
+    env: &Environment,
+    tile_map: &TileMap,
+    fov: &FieldOfView,
+    occupied: &mut HashSet<IVec2>,
+    rng: &mut impl Rng,
+) -> (Action, Option<String>) {
+    let pos: IVec2 = (*position).into();
+    let offset = player - pos;
+    if offset.abs().element_sum() == 1 {
+        let damage = (monster.strength as f32 * env.power(&monster.abilities))
+            .round()
+            .max(1.0);
+        stats.hp = stats.hp.saturating_sub(damage as u32);
+        let notice = if stats.hp == 0 {
+            format!("The {} strikes you down!", monster.name)
+        } else {
+            format!("The {} hits you! ({} HP left)", monster.name, stats.hp)
+        };
+        return (Action::Attack, Some(notice));
+    }
+
+    let free = |to: IVec2| tile_map.is_walkable(to) && !occupied.contains(&to) && to != player;
+    let step = if fov.is_visible(pos) && offset.abs().max_element() <= CHASE_RADIUS {
+        let mut steps = STEPS;
+        steps.sort_by_key(|&step| (offset - step).abs().element_sum());
+        steps.into_iter().find(|&step| free(pos + step))
+    } else if rng.gen_bool(WANDER_CHANCE) {
+        STEPS.choose(rng).copied().filter(|&step| free(pos + step))
+    } else {
+        None
+    };
+
+    let Some(step) = step else {
+        return (Action::Wait, None);
+    };
+    let to = pos + step;
+    occupied.remove(&pos);
+    occupied.insert(to);
+    *position = to.into();
+    let cost = env.movement_cost(tile_map, to);
+    (Action::Move { cost }, None)
+}
+
+/// The turn scheduler. Once the player has acted and spent the energy for
+/// it, run the world on a tick at a time, every actor saving up energy by
+/// its speed and each yokai taking a turn whenever it has enough, until the
+/// player can act again. The game then waits on the player's input.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn run_turns(
+    mut actions: EventReader<PlayerActed>,
+    mut passed: EventWriter<TimePassed>,
+    env: Environment,
+    tile_map: Option<Res<TileMap>>,
+    fov: Res<FieldOfView>,
+    mut world_rng: ResMut<WorldRng>,
+    mut player_query: Query<(&GridPosition, &mut Actor, &mut PlayerStats), With<PlayerBody>>,
+    mut monster_query: Query<
+        (Entity, &mut GridPosition, &mut Actor, &Monster),
+        Without<PlayerBody>,
+    >,
+    mut message_query: Query<(&mut Text2d, &mut CombatMessage)>,
+) {
+    let Some(tile_map) = tile_map else {
+        return;
+    };
+    let Ok((&player, mut player_actor, mut stats)) = player_query.get_single_mut() else {
+        return;
+    };
+    for acted in actions.read() {
+        player_actor.spend(acted.action);
+    }
+    if player_actor.is_ready() {
+        return;
+    }
+
+    let player: IVec2 = player.into();
+    let mut occupied: HashSet<IVec2> = monster_query
+        .iter()
+        .filter(|(_, _, _, monster)| monster.is_alive)
+        .map(|(_, &position, _, _)| position.into())
+        .collect();
+    let mut notice = None;
+    let mut ticks = 0;
+    while !player_actor.is_ready() && ticks < MAX_TICKS {
+        ticks += 1;
+        player_actor.recover();
+        for (_, _, mut actor, monster) in monster_query.iter_mut() {
+            if monster.is_alive {
+                actor.recover();
+            }
+        }
+
+        // Yokai take their turns in a fixed order so a seed plays out the same
+        let mut ready: Vec<Entity> = monster_query
+            .iter()
+            .filter(|(_, _, actor, monster)| monster.is_alive && actor.is_ready())
+            .map(|(entity, _, _, _)| entity)
+            .collect();
+        ready.sort();
+        for entity in ready {
+            let Ok((_, mut position, mut actor, monster)) = monster_query.get_mut(entity) else {
+                continue;
+            };
+            while actor.is_ready() {
+                let (action, told) = monster_turn(
+                    &mut position,
+                    monster,
+                    player,
+                    &mut stats,
+                    &env,
+                    &tile_map,
+                    &fov,
+                    &mut occupied,
+                    &mut world_rng.0,
+                );
+                actor.spend(action);
+                notice = told.or(notice);
+            }
+        }
+    }
+    passed.send(TimePassed { ticks });
+
+    if let Some(notice) = notice {
+        if let Ok((mut text, mut message)) = message_query.get_single_mut() {
+            message.message = notice;
+            text.0 = message.message.clone();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        map::{biome::Biome, terrain::TerrainCatalog, tile_grid::TileGrid},
+        time::GameClock,
+        weather::Weather,
+    };
+    use bevy::ecs::{event::Events, system::RunSystemOnce};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    /// A world with the player standing on open floor at `(1, 1)`, ready to
+    /// act.
+    fn world() -> World {
+        let terrain = TerrainCatalog::base();
+        let mut rng = StdRng::seed_from_u64(0);
+        let grid = TileGrid::from_fn(IVec2::splat(8), |_| {
+            (terrain.item("dungeon_floor", &mut rng), Biome::Ruins)
+        });
+
+        let mut world = World::new();
+        world.insert_resource(TileMap::from_grid(&grid, &terrain, 16.0, &mut rng));
+        world.insert_resource(WorldRng(rng));
+        world.init_resource::<FieldOfView>();
+        world.init_resource::<GameClock>();
+        world.init_resource::<Weather>();
+        world.init_resource::<Events<PlayerActed>>();
+        world.init_resource::<Events<TimePassed>>();
+        world.spawn((
+            GridPosition { x: 1, y: 1 },
+            Actor {
+                speed: TURN_ENERGY,
+                energy: TURN_ENERGY,
+            },
+            PlayerStats::default(),
+            PlayerBody,
+        ));
+        world
+    }
+
+    /// Spawn a yokai next to the player, so it attacks every turn.
+    fn spawn_neighbour(world: &mut World, speed: i32) {
+        world.spawn((
+            GridPosition { x: 2, y: 1 },
+            Actor::new(speed),
+            Monster {
+                hp: 10,
+                max_hp: 10,
+                strength: 1,
+                name: String::from("Oni"),
+                is_alive: true,
+                abilities: Vec::new(),
+            },
+        ));
+    }
+
+    /// Have the player take `action` from exactly a turn's energy and run
+    /// the scheduler, returning how many ticks passed and the player's hp.
+    fn play(world: &mut World, action: Action) -> (usize, u32) {
+        world
+            .query_filtered::<&mut Actor, With<PlayerBody>>()
+            .single_mut(world)
+            .energy = TURN_ENERGY;
+        world.send_event(PlayerActed { action });
+        world.run_system_once(run_turns).unwrap();
+        world.resource_mut::<Events<PlayerActed>>().clear();
+
+        let ticks = world
+            .resource_mut::<Events<TimePassed>>()
+            .drain()
+            .map(|passed| passed.ticks)
+            .sum();
+        let hp = world.query::<&PlayerStats>().single(world).hp;
+        (ticks, hp)
+    }
+
+    #[test]
+    fn time_passes_by_the_energy_the_player_spent() {
+        let mut world = world();
+        assert_eq!(play(&mut world, Action::Move { cost: 1.0 }).0, 1);
+        assert_eq!(play(&mut world, Action::UseItem).0, 2);
+        assert_eq!(play(&mut world, Action::Move { cost: 3.0 }).0, 3);
+    }
+
+    #[test]
+    fn a_ready_player_is_not_kept_waiting() {
+        let mut world = world();
+        world.run_system_once(run_turns).unwrap();
+        assert!(world.resource::<Events<TimePassed>>().is_empty());
+    }
+
+    #[test]
+    fn yokai_act_by_their_speed() {
+        let mut world = world();
+        let full_hp = PlayerStats::default().hp;
+        spawn_neighbour(&mut world, 240);
+        // Twice the player's speed, less the cost of attacking
+        assert_eq!(
+            play(&mut world, Action::Move { cost: 1.0 }),
+            (1, full_hp - 2)
+        );
+
+        let mut world = self::world();
+        spawn_neighbour(&mut world, 60);
+        assert_eq!(play(&mut world, Action::Move { cost: 1.0 }), (1, full_hp));
+        assert_eq!(
+            play(&mut world, Action::Move { cost: 1.0 }),
+            (1, full_hp - 1)
+        );
+    }
+}
@@ -0,0 +1,367 @@
+use crate::{
+    components::*,
+    create_text_color_bundle,
+    map::tile_map::TileMap,
+    seed::WorldRng,
+    systems::{Environment, FieldOfView},
+    time::{get_season, GameClock, Season, TICKS_PER_DAY, TICKS_PER_HOUR},
+    weather::{Weather, WeatherChanged, WeatherKind},
+    MainCamera,
+};
+use bevy::{prelude::*, window::PrimaryWindow};
+use rand::{seq::SliceRandom, Rng};
+
+/// Move the weather along with the clock: every tick that passes ramps it,
+/// and each hour may bring a change.
+pub fn update_weather(
+    clock: Res<GameClock>,
+    mut last_tick: Local<Option<usize>>,
+    mut weather: ResMut<Weather>,
+    mut world_rng: ResMut<WorldRng>,
+    mut events: EventWriter<WeatherChanged>,
+) {
+    if !clock.is_changed() {
+        return;
+    }
+    let from = last_tick.unwrap_or(clock.tick);
+    *last_tick = Some(clock.tick);
+
+    for tick in from + 1..=clock.tick {
+        weather.ramp();
+        if tick.is_multiple_of(TICKS_PER_HOUR) {
+            let season = get_season(tick / TICKS_PER_DAY);
+            if let Some(change) = weather.roll(season, &mut world_rng.0) {
+                events.send(change);
+            }
+        }
+    }
+}
+
+/// Tell the player when the weather turns.
+pub fn announce_weather(
+    mut events: EventReader<WeatherChanged>,
+    mut message_query: Query<(&mut Text2d, &mut CombatMessage)>,
+) {
+    let Some(event) = events.read().last() else {
+        return;
+    };
+    let notice = match event.to {
+        WeatherKind::Clear => "The sky clears.",
+        WeatherKind::Rain => "Rain begins to fall.",
+        WeatherKind::Snow => "Snow begins to fall.",
+        WeatherKind::Fog => "A fog rolls in.",
+        WeatherKind::Storm => "A storm breaks overhead.",
+    };
+    debug!("Weather: {} -> {}", event.from.name(), event.to.name());
+    if let Ok((mut text, mut message)) = message_query.get_single_mut() {
+        message.message = String::from(notice);
+        text.0 = message.message.clone();
+    }
+}
+
+/// Ticks footprints last in the snow while none is falling; falling snow
+/// fills them in faster.
+const TRACK_TICKS: usize = 2 * TICKS_PER_HOUR;
+/// Height footprints are drawn at, above the ground but below walkers.
+const TRACK_Z: f32 = 0.25;
+
+/// Footprints left in the snow by the player or a yokai.
+#[derive(Component)]
+pub struct Track {
+    /// Tick the footprints were left on.
+    pub left_at: usize,
+}
+
+/// Leave footprints wherever the player or a yokai steps while snow falls
+/// thick enough or lies on the ground for winter, and let old ones fill in,
+/// faster under falling snow.
+#[allow(clippy::type_complexity)]
+pub fn leave_tracks(
+    mut commands: Commands,
+    env: Environment,
+    tile_map: Option<Res<TileMap>>,
+    asset_server: Res<AssetServer>,
+    walker_query: Query<Ref<GridPosition>, Or<(With<PlayerBody>, With<Monster>)>>,
+    track_query: Query<(Entity, &GridPosition, &Track)>,
+) {
+    let Some(tile_map) = tile_map else {
+        return;
+    };
+    let tick = env.clock().tick;
+    let snow = env.weather().intensity_of(WeatherKind::Snow);
+    for (entity, _, track) in track_query.iter() {
+        let age = (tick - track.left_at) as f32 * (1.0 + snow);
+        if age >= TRACK_TICKS as f32 {
+            commands.entity(entity).despawn();
+        }
+    }
+
+    let font = asset_server.load("fonts/NotoSansJP-VariableFont_wght.ttf");
+    let color = Color::srgb(0.7, 0.75, 0.85);
+    for position in walker_query.iter() {
+        let pos: IVec2 = (*position).into();
+        let snowy = tile_map
+            .map_item(pos)
+            .is_some_and(|item| item.season == Some(Season::Winter));
+        let tracked = track_query.iter().any(|(_, &track, _)| track == *position);
+        if !position.is_changed()
+            || position.is_added()
+            || !(snowy || env.leaves_tracks())
+            || tracked
+            || tile_map.is_water(pos)
+        {
+            continue;
+        }
+        let world = tile_map.grid_to_world(pos);
+        commands.spawn((
+            create_text_color_bundle(font.clone(), "∴", world.x, world.y, TRACK_Z, color),
+            BaseColor(color),
+            Track { left_at: tick },
+            *position,
+        ));
+    }
+}
+
+/// Chance each hour of a storm at full strength doing something more than
+/// blowing.
+const STORM_EVENT_CHANCE: f64 = 0.6;
+/// Ticks the flash of a lightning strike lights the ground.
+const STRIKE_TICKS: usize = 5;
+
+/// Where lightning has just struck, lit up until `until`.
+#[derive(Component)]
+pub struct Strike {
+    pub until: usize,
+}
+
+/// Once an hour, a storm may throw up something: a peal of thunder, a
+/// lightning strike that lights up the ground where it lands, or powerful
+/// yokai riding in on the wind. Rolled from the world seed, as the events
+/// change the world.
+pub fn storm_events(
+    mut commands: Commands,
+    env: Environment,
+    mut last_hour: Local<Option<usize>>,
+    fov: Res<FieldOfView>,
+    mut world_rng: ResMut<WorldRng>,
+    strike_query: Query<(Entity, &Strike)>,
+    mut message_query: Query<(&mut Text2d, &mut CombatMessage)>,
+) {
+    let tick = env.clock().tick;
+    for (entity, strike) in strike_query.iter() {
+        if strike.until <= tick {
+            commands.entity(entity).despawn();
+        }
+    }
+
+    let hour = tick / TICKS_PER_HOUR;
+    if last_hour.replace(hour).is_none_or(|last| last == hour) {
+        return;
+    }
+    let rng = &mut world_rng.0;
+    let chance = (STORM_EVENT_CHANCE * env.storm() as f64).clamp(0.0, 1.0);
+    if !rng.gen_bool(chance) {
+        return;
+    }
+
+    let notice = match rng.gen_range(0..3) {
+        0 => "Thunder rolls over the hills.",
+        1 => {
+            // Sorted so the strike lands on the same tile for the same seed
+            let mut visible: Vec<IVec2> = fov.visible.iter().copied().collect();
+            visible.sort_by_key(|pos| (pos.x, pos.y));
+            let Some(&pos) = visible.choose(rng) else {
+                return;
+            };
+            commands.spawn((
+                Strike {
+                    until: tick + STRIKE_TICKS,
+                },
+                LightSource {
+                    color: Vec3::new(0.8, 0.85, 1.0),
+                    radius: 4,
+                    fire: false,
+                },
+                GridPosition::from(pos),
+            ));
+            "Lightning strikes nearby!"
+        }
+        _ => {
+            commands.spawn(SpawnMonstersMarker);
+            "Something rides in on the storm."
+        }
+    };
+    if let Ok((mut text, mut message)) = message_query.get_single_mut() {
+        message.message = String::from(notice);
+        text.0 = message.message.clone();
+    }
+}
+
+/// Raindrops in view per square tile at full intensity.
+const RAIN_DENSITY: f32 = 0.03;
+/// Snowflakes in view per square tile at full intensity.
+const SNOW_DENSITY: f32 = 0.02;
+/// Height above the terrain, monsters and items that weather is drawn at.
+const OVERLAY_Z: f32 = 1.5;
+/// Chance per second of lightning during a storm at full strength.
+const LIGHTNING_RATE: f32 = 0.2;
+/// Seconds a lightning flash takes to fade.
+const FLASH_SECONDS: f32 = 0.25;
+
+/// A raindrop or snowflake falling across the view. Purely visual: it moves
+/// in real time and never touches the map.
+#[derive(Component)]
+pub struct Precipitation {
+    pub kind: WeatherKind,
+    /// World units per second.
+    pub velocity: Vec2,
+    /// Offset into the sideways drift of snow, so flakes don't sway in step.
+    pub phase: f32,
+}
+
+/// A flash of lightning lit over the whole view, fading out.
+#[derive(Component)]
+pub struct LightningFlash {
+    pub remaining: f32,
+}
+
+fn random_in(rect: Rect, rng: &mut impl Rng) -> Vec2 {
+    Vec2::new(
+        rng.gen_range(rect.min.x..=rect.max.x),
+        rng.gen_range(rect.min.y..=rect.max.y),
+    )
+}
+
+/// Wrap a coordinate back into `min..max`, so drops leaving one side of the
+/// view come back in at the other.
+fn wrap(value: f32, min: f32, max: f32) -> f32 {
+    min + (value - min).rem_euclid(max - min)
+}
+
+/// Keep the view filled with rain and snow as dense as the weather is
+/// intense, and let it fall. Storm rain is heavier and driven by the wind.
+/// The overlay draws from its own RNG so it never disturbs the world seed.
+#[allow(clippy::too_many_arguments)]
+pub fn update_precipitation(
+    mut commands: Commands,
+    time: Res<Time>,
+    weather: Res<Weather>,
+    tile_map: Option<Res<TileMap>>,
+    asset_server: Res<AssetServer>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<&Transform, (With<MainCamera>, Without<Precipitation>)>,
+    mut drop_query: Query<(Entity, &mut Transform, &Precipitation)>,
+) {
+    let (Some(tile_map), Ok(window), Ok(camera)) = (
+        tile_map,
+        window_query.get_single(),
+        camera_query.get_single(),
+    ) else {
+        return;
+    };
+    let view = Rect::from_center_size(camera.translation.truncate(), window.size());
+    let view_tiles = view.size().element_product() / (tile_map.tile_size * tile_map.tile_size);
+    let rng = &mut rand::thread_rng();
+
+    let storm = weather.intensity_of(WeatherKind::Storm);
+    let rain = (weather.intensity_of(WeatherKind::Rain) + storm).min(1.0);
+    let snow = weather.intensity_of(WeatherKind::Snow);
+    let wanted = [
+        (
+            WeatherKind::Rain,
+            (rain * RAIN_DENSITY * view_tiles) as usize,
+        ),
+        (
+            WeatherKind::Snow,
+            (snow * SNOW_DENSITY * view_tiles) as usize,
+        ),
+    ];
+
+    let font = asset_server.load("fonts/NotoSansJP-VariableFont_wght.ttf");
+    for (kind, wanted) in wanted {
+        let mut count = 0;
+        for (entity, _, drop) in drop_query.iter() {
+            if drop.kind == kind {
+                count += 1;
+                if count > wanted {
+                    commands.entity(entity).despawn();
+                }
+            }
+        }
+        for _ in count..wanted {
+            let pos = random_in(view, rng);
+            let (glyph, color, velocity) = match kind {
+                WeatherKind::Snow => (
+                    if rng.gen_bool(0.5) { "*" } else { "・" },
+                    Color::srgba(0.95, 0.95, 1.0, 0.8),
+                    Vec2::new(0.0, -rng.gen_range(20.0..40.0)),
+                ),
+                _ => (
+                    if storm > 0.0 { "/" } else { "|" },
+                    Color::srgba(0.6, 0.7, 0.9, 0.6),
+                    Vec2::new(-storm * 150.0, -rng.gen_range(300.0..400.0)),
+                ),
+            };
+            commands.spawn((
+                create_text_color_bundle(font.clone(), glyph, pos.x, pos.y, OVERLAY_Z, color),
+                Precipitation {
+                    kind,
+                    velocity,
+                    phase: rng.gen_range(0.0..std::f32::consts::TAU),
+                },
+            ));
+        }
+    }
+
+    let elapsed = time.elapsed_secs();
+    for (_, mut transform, drop) in drop_query.iter_mut() {
+        let mut step = drop.velocity * time.delta_secs();
+        if drop.kind == WeatherKind::Snow {
+            step.x += (elapsed + drop.phase).sin() * 15.0 * time.delta_secs();
+        }
+        let pos = transform.translation.truncate() + step;
+        transform.translation.x = wrap(pos.x, view.min.x, view.max.x);
+        transform.translation.y = wrap(pos.y, view.min.y, view.max.y);
+    }
+}
+
+/// Now and then during a storm, flash the whole view white and let it fade.
+pub fn flash_lightning(
+    mut commands: Commands,
+    time: Res<Time>,
+    weather: Res<Weather>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<&Transform, (With<MainCamera>, Without<LightningFlash>)>,
+    mut flash_query: Query<(Entity, &mut Sprite, &mut Transform, &mut LightningFlash)>,
+) {
+    let (Ok(window), Ok(camera)) = (window_query.get_single(), camera_query.get_single()) else {
+        return;
+    };
+
+    for (entity, mut sprite, mut transform, mut flash) in flash_query.iter_mut() {
+        flash.remaining -= time.delta_secs();
+        if flash.remaining <= 0.0 {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        sprite.color = Color::srgba(0.9, 0.9, 1.0, 0.4 * flash.remaining / FLASH_SECONDS);
+        transform.translation.x = camera.translation.x;
+        transform.translation.y = camera.translation.y;
+    }
+
+    let storm = weather.intensity_of(WeatherKind::Storm);
+    let chance = (LIGHTNING_RATE * storm * time.delta_secs()).clamp(0.0, 1.0) as f64;
+    if flash_query.is_empty() && rand::thread_rng().gen_bool(chance) {
+        commands.spawn((
+            Sprite {
+                color: Color::srgba(0.9, 0.9, 1.0, 0.4),
+                custom_size: Some(window.size()),
+                ..default()
+            },
+            Transform::from_xyz(camera.translation.x, camera.translation.y, OVERLAY_Z + 1.0),
+            LightningFlash {
+                remaining: FLASH_SECONDS,
+            },
+        ));
+    }
+}
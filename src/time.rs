@@ -0,0 +1,270 @@
+use crate::components::Action;
+use bevy::prelude::*;
+use serde::Deserialize;
+
+pub const TICKS_PER_DAY: usize = 1200;
+pub const WITCHING_HOUR: usize = TICKS_PER_DAY / 6;
+pub const SUNRISE: usize = TICKS_PER_DAY / 4;
+pub const SUNSET: usize = TICKS_PER_DAY * 3 / 4;
+pub const TICKS_PER_HOUR: usize = TICKS_PER_DAY / 24;
+pub const DAYS_PER_MONTH: usize = 28;
+pub const DAYS_PER_YEAR: usize = DAYS_PER_MONTH * 12;
+pub const DAYS_PER_SEASON: usize = DAYS_PER_YEAR / 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+pub enum Moonphases {
+    New,
+    WaxingCrescent,
+    FirstQuarter,
+    WaxingGibbous,
+    Full,
+    WaningGibbous,
+    LastQuarter,
+    WaningCrescent,
+}
+
+impl Moonphases {
+    /// How stirred up yokai are under this moon, as a multiplier on how many
+    /// roam at once. The dark of the new moon and the full moon are the peaks.
+    pub fn yokai_activity(self) -> f32 {
+        match self {
+            Moonphases::New | Moonphases::Full => 1.5,
+            Moonphases::WaxingGibbous | Moonphases::WaningGibbous => 1.25,
+            Moonphases::WaxingCrescent | Moonphases::WaningCrescent => 1.1,
+            Moonphases::FirstQuarter | Moonphases::LastQuarter => 1.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+pub enum Season {
+    Spring,
+    Summer,
+    Autumn,
+    Winter,
+}
+
+impl Season {
+    /// The season that comes before this one.
+    pub fn previous(self) -> Self {
+        match self {
+            Season::Spring => Season::Winter,
+            Season::Summer => Season::Spring,
+            Season::Autumn => Season::Summer,
+            Season::Winter => Season::Autumn,
+        }
+    }
+}
+
+/// Coarse parts of the day that yokai keep to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+pub enum TimePeriod {
+    Night,
+    /// The hour when the barrier between worlds is thinnest.
+    WitchingHour,
+    Dawn,
+    Day,
+    Dusk,
+}
+
+impl TimePeriod {
+    /// The period a tick of the day falls in.
+    pub fn at(time_of_day: usize) -> Self {
+        if time_of_day < WITCHING_HOUR {
+            TimePeriod::Night
+        } else if time_of_day < WITCHING_HOUR + TICKS_PER_HOUR {
+            TimePeriod::WitchingHour
+        } else if time_of_day < SUNRISE {
+            TimePeriod::Night
+        } else if time_of_day < SUNRISE + TICKS_PER_HOUR {
+            TimePeriod::Dawn
+        } else if time_of_day < SUNSET - TICKS_PER_HOUR {
+            TimePeriod::Day
+        } else if time_of_day < SUNSET {
+            TimePeriod::Dusk
+        } else {
+            TimePeriod::Night
+        }
+    }
+}
+
+/// The moon on `day`, with the month split evenly between the eight phases
+/// so the full moon rises mid-month.
+pub fn get_moon_phase(day: usize) -> Moonphases {
+    let moon_day = day % DAYS_PER_MONTH;
+    match moon_day * 8 / DAYS_PER_MONTH {
+        0 => Moonphases::New,
+        1 => Moonphases::WaxingCrescent,
+        2 => Moonphases::FirstQuarter,
+        3 => Moonphases::WaxingGibbous,
+        4 => Moonphases::Full,
+        5 => Moonphases::WaningGibbous,
+        6 => Moonphases::LastQuarter,
+        _ => Moonphases::WaningCrescent,
+    }
+}
+
+pub fn get_season(day: usize) -> Season {
+    match day % DAYS_PER_YEAR / DAYS_PER_SEASON {
+        0 => Season::Spring,
+        1 => Season::Summer,
+        2 => Season::Autumn,
+        _ => Season::Winter,
+    }
+}
+
+/// Sent whenever the player spends their turn.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct PlayerActed {
+    pub action: Action,
+}
+
+/// Sent when the scheduler has run the world on to the player's next turn,
+/// with how many ticks that took.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct TimePassed {
+    pub ticks: usize,
+}
+
+/// Sent when the clock moves into a new part of the day.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct PeriodChanged {
+    pub period: TimePeriod,
+}
+
+/// Sent at midnight, when a new day begins.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct DayChanged {
+    pub day: usize,
+}
+
+/// Sent on the first day of each season.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct SeasonChanged {
+    pub season: Season,
+}
+
+/// In-game time, counted in ticks since the first midnight. The game starts
+/// at sunrise on the first day and a tick passes with each player action.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct GameClock {
+    pub tick: usize,
+}
+
+impl Default for GameClock {
+    fn default() -> Self {
+        Self { tick: SUNRISE }
+    }
+}
+
+impl GameClock {
+    /// Let one tick pass.
+    pub fn advance(&mut self) {
+        self.tick += 1;
+    }
+
+    pub fn day(&self) -> usize {
+        self.tick / TICKS_PER_DAY
+    }
+
+    /// Days since the start of the year, from 0.
+    pub fn day_of_year(&self) -> usize {
+        self.day() % DAYS_PER_YEAR
+    }
+
+    /// Ticks since the last midnight.
+    pub fn time_of_day(&self) -> usize {
+        self.tick % TICKS_PER_DAY
+    }
+
+    pub fn period(&self) -> TimePeriod {
+        TimePeriod::at(self.time_of_day())
+    }
+
+    pub fn moon_phase(&self) -> Moonphases {
+        get_moon_phase(self.day())
+    }
+
+    pub fn season(&self) -> Season {
+        get_season(self.day())
+    }
+
+    /// Days since the current season began, counting the part of today
+    /// already gone.
+    pub fn days_into_season(&self) -> f32 {
+        (self.tick % (DAYS_PER_SEASON * TICKS_PER_DAY)) as f32 / TICKS_PER_DAY as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn periods_change_on_the_hour() {
+        let periods = [
+            (0, TimePeriod::Night),
+            (WITCHING_HOUR - 1, TimePeriod::Night),
+            (WITCHING_HOUR, TimePeriod::WitchingHour),
+            (WITCHING_HOUR + TICKS_PER_HOUR - 1, TimePeriod::WitchingHour),
+            (WITCHING_HOUR + TICKS_PER_HOUR, TimePeriod::Night),
+            (SUNRISE, TimePeriod::Dawn),
+            (SUNRISE + TICKS_PER_HOUR, TimePeriod::Day),
+            (SUNSET - TICKS_PER_HOUR - 1, TimePeriod::Day),
+            (SUNSET - TICKS_PER_HOUR, TimePeriod::Dusk),
+            (SUNSET, TimePeriod::Night),
+            (TICKS_PER_DAY - 1, TimePeriod::Night),
+        ];
+        for (tick, period) in periods {
+            assert_eq!(TimePeriod::at(tick), period, "tick {tick}");
+        }
+    }
+
+    #[test]
+    fn the_moon_waxes_and_wanes_each_month() {
+        assert_eq!(get_moon_phase(0), Moonphases::New);
+        assert_eq!(get_moon_phase(DAYS_PER_MONTH / 2), Moonphases::Full);
+        assert_eq!(
+            get_moon_phase(DAYS_PER_MONTH - 1),
+            Moonphases::WaningCrescent
+        );
+        assert_eq!(get_moon_phase(DAYS_PER_MONTH), Moonphases::New);
+    }
+
+    #[test]
+    fn the_clock_starts_at_sunrise_on_the_first_day() {
+        let clock = GameClock::default();
+        assert_eq!((clock.day(), clock.time_of_day()), (0, SUNRISE));
+        assert_eq!(clock.period(), TimePeriod::Dawn);
+        assert_eq!(clock.season(), Season::Spring);
+        assert_eq!(clock.moon_phase(), Moonphases::New);
+    }
+
+    #[test]
+    fn days_roll_over_at_midnight() {
+        let mut clock = GameClock {
+            tick: TICKS_PER_DAY - 1,
+        };
+        assert_eq!(clock.day(), 0);
+        clock.advance();
+        assert_eq!((clock.day(), clock.time_of_day()), (1, 0));
+        assert_eq!(clock.period(), TimePeriod::Night);
+    }
+
+    #[test]
+    fn seasons_and_years_roll_over() {
+        let at_day = |day: usize| GameClock {
+            tick: day * TICKS_PER_DAY + TICKS_PER_DAY / 2,
+        };
+        assert_eq!(at_day(DAYS_PER_SEASON - 1).season(), Season::Spring);
+        assert_eq!(at_day(DAYS_PER_SEASON).season(), Season::Summer);
+        assert_eq!(at_day(DAYS_PER_SEASON * 3).season(), Season::Winter);
+        assert_eq!(at_day(DAYS_PER_SEASON).days_into_season(), 0.5);
+
+        let new_year = at_day(DAYS_PER_YEAR);
+        assert_eq!(new_year.season(), Season::Spring);
+        assert_eq!(new_year.day_of_year(), 0);
+        assert_eq!(new_year.day(), DAYS_PER_YEAR);
+        assert_eq!(new_year.days_into_season(), 0.5);
+    }
+}
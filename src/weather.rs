@@ -0,0 +1,289 @@
+use crate::{
+    bestiary::Ability,
+    time::{Season, TICKS_PER_HOUR},
+};
+use bevy::prelude::*;
+use rand::{seq::SliceRandom, Rng};
+use serde::Deserialize;
+
+/// How much a spell of weather strengthens or fades each tick, so a change
+/// takes about an hour to set in fully.
+const RAMP_PER_TICK: f32 = 1.0 / TICKS_PER_HOUR as f32;
+/// Weight of the weather carrying on for another hour, against the
+/// season's weights for each kind of weather taking over.
+const PERSISTENCE: u32 = 60;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+pub enum WeatherKind {
+    /// 晴れ
+    Clear,
+    /// 雨
+    Rain,
+    /// 雪
+    Snow,
+    /// 霧
+    Fog,
+    /// 嵐
+    Storm,
+}
+
+impl WeatherKind {
+    pub const ALL: [WeatherKind; 5] = [
+        WeatherKind::Clear,
+        WeatherKind::Rain,
+        WeatherKind::Snow,
+        WeatherKind::Fog,
+        WeatherKind::Storm,
+    ];
+
+    /// How likely each season is to bring on this weather.
+    fn season_weight(self, season: Season) -> u32 {
+        match (season, self) {
+            (Season::Spring, WeatherKind::Clear) => 4,
+            (Season::Spring, WeatherKind::Rain) => 4,
+            (Season::Spring, WeatherKind::Fog) => 2,
+            (Season::Spring, WeatherKind::Storm) => 1,
+            (Season::Summer, WeatherKind::Clear) => 6,
+            (Season::Summer, WeatherKind::Rain) => 3,
+            (Season::Summer, WeatherKind::Fog) => 1,
+            (Season::Summer, WeatherKind::Storm) => 2,
+            (Season::Autumn, WeatherKind::Clear) => 5,
+            (Season::Autumn, WeatherKind::Rain) => 3,
+            (Season::Autumn, WeatherKind::Fog) => 3,
+            (Season::Autumn, WeatherKind::Storm) => 2,
+            (Season::Winter, WeatherKind::Clear) => 5,
+            (Season::Winter, WeatherKind::Rain) => 1,
+            (Season::Winter, WeatherKind::Snow) => 5,
+            (Season::Winter, WeatherKind::Fog) => 2,
+            (Season::Winter, WeatherKind::Storm) => 1,
+            (_, WeatherKind::Snow) => 0,
+        }
+    }
+
+    /// Relative chance of the weather turning from `self` to `next` in an
+    /// hour of `season`. Weather the season allows tends to linger, and
+    /// storms tend to blow themselves out into rain.
+    fn transition_weight(self, next: WeatherKind, season: Season) -> u32 {
+        let weight = next.season_weight(season);
+        if weight == 0 {
+            0
+        } else if next == self {
+            PERSISTENCE
+        } else if self == WeatherKind::Storm && next == WeatherKind::Rain {
+            weight + PERSISTENCE / 2
+        } else {
+            weight
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            WeatherKind::Clear => "clear",
+            WeatherKind::Rain => "rain",
+            WeatherKind::Snow => "snow",
+            WeatherKind::Fog => "fog",
+            WeatherKind::Storm => "storm",
+        }
+    }
+}
+
+/// Sent when a new kind of weather starts to set in.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct WeatherChanged {
+    pub from: WeatherKind,
+    pub to: WeatherKind,
+}
+
+/// The weather over the world. It changes on the hour by a Markov chain
+/// whose odds depend on the season, and each change ramps in gradually while
+/// the weather before it fades out.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct Weather {
+    pub kind: WeatherKind,
+    /// How strongly `kind` has set in, from 0 up to `peak`.
+    pub intensity: f32,
+    /// How strong this spell of weather will get, from 0 to 1.
+    pub peak: f32,
+    /// The weather being left behind.
+    pub previous: WeatherKind,
+    /// How much of `previous` lingers, fading to 0.
+    pub previous_intensity: f32,
+}
+
+impl Default for Weather {
+    fn default() -> Self {
+        Self {
+            kind: WeatherKind::Clear,
+            intensity: 1.0,
+            peak: 1.0,
+            previous: WeatherKind::Clear,
+            previous_intensity: 0.0,
+        }
+    }
+}
+
+impl Weather {
+    /// How strong `kind` weather is right now, counting both the weather
+    /// setting in and the weather fading out.
+    pub fn intensity_of(&self, kind: WeatherKind) -> f32 {
+        let mut intensity = 0.0;
+        if self.kind == kind {
+            intensity += self.intensity;
+        }
+        if self.previous == kind {
+            intensity += self.previous_intensity;
+        }
+        intensity.min(1.0)
+    }
+
+    /// How wet it is out: rain, and the heavier rain of a storm.
+    pub fn wetness(&self) -> f32 {
+        (self.intensity_of(WeatherKind::Rain) + self.intensity_of(WeatherKind::Storm)).min(1.0)
+    }
+
+    /// Multiplier on the cost of every step: snow slows walkers down and
+    /// storm winds push against them.
+    pub fn movement_multiplier(&self) -> f32 {
+        1.0 + 0.5 * self.intensity_of(WeatherKind::Snow)
+            + 0.25 * self.intensity_of(WeatherKind::Storm)
+    }
+
+    /// Multiplier on how far anyone can see: fog closes in the most, rain
+    /// and snow less so.
+    pub fn sight_multiplier(&self) -> f32 {
+        let haze = 0.6 * self.intensity_of(WeatherKind::Fog)
+            + 0.2 * self.wetness()
+            + 0.2 * self.intensity_of(WeatherKind::Snow);
+        (1.0 - haze).max(0.3)
+    }
+
+    /// Multiplier on how many yokai roam: they come out under cover of fog
+    /// and the most powerful ride in on storms.
+    pub fn spawn_multiplier(&self) -> f32 {
+        1.0 + 0.5 * self.intensity_of(WeatherKind::Fog)
+            + 0.25 * self.intensity_of(WeatherKind::Storm)
+    }
+
+    /// Multiplier on flames, from torches to a kitsune's fire breath; rain
+    /// damps them down.
+    pub fn fire_multiplier(&self) -> f32 {
+        1.0 - 0.5 * self.wetness()
+    }
+
+    /// Multiplier on the power of a yokai with `ability`: fire falters in
+    /// the wet, which water yokai thrive in.
+    pub fn ability_multiplier(&self, ability: Ability) -> f32 {
+        match ability {
+            Ability::Fire => self.fire_multiplier(),
+            Ability::Swim => 1.0 + 0.5 * self.wetness(),
+            _ => 1.0,
+        }
+    }
+
+    /// The kind of weather most felt right now.
+    pub fn dominant(&self) -> WeatherKind {
+        if self.previous_intensity > self.intensity {
+            self.previous
+        } else {
+            self.kind
+        }
+    }
+
+    /// Let a tick pass, ramping the current weather towards its peak and
+    /// fading out the previous weather.
+    pub fn ramp(&mut self) {
+        self.intensity = (self.intensity + RAMP_PER_TICK).min(self.peak);
+        self.previous_intensity = (self.previous_intensity - RAMP_PER_TICK).max(0.0);
+    }
+
+    /// Roll the hourly change of weather for `season`. Returns the event to
+    /// send if the weather turned.
+    pub fn roll(&mut self, season: Season, rng: &mut impl Rng) -> Option<WeatherChanged> {
+        let current = self.kind;
+        let next = WeatherKind::ALL
+            .choose_weighted(rng, |&next| current.transition_weight(next, season))
+            .copied()
+            .unwrap_or(WeatherKind::Clear);
+        if next == current {
+            return None;
+        }
+
+        *self = Weather {
+            kind: next,
+            intensity: 0.0,
+            peak: rng.gen_range(0.4..=1.0),
+            previous: current,
+            previous_intensity: self.intensity,
+        };
+        Some(WeatherChanged {
+            from: current,
+            to: next,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    /// The weather each hour for a week of `season` from a seeded start.
+    fn forecast(season: Season, seed: u64) -> Vec<WeatherKind> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut weather = Weather::default();
+        (0..24 * 7)
+            .map(|_| {
+                weather.roll(season, &mut rng);
+                weather.kind
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rolls_are_reproducible_from_a_seed() {
+        assert_eq!(forecast(Season::Spring, 3), forecast(Season::Spring, 3));
+        assert_ne!(forecast(Season::Spring, 3), forecast(Season::Spring, 4));
+    }
+
+    #[test]
+    fn snow_only_falls_in_winter() {
+        for season in [Season::Spring, Season::Summer, Season::Autumn] {
+            for seed in 0..10 {
+                assert!(!forecast(season, seed).contains(&WeatherKind::Snow));
+            }
+        }
+        assert!((0..10).any(|seed| forecast(Season::Winter, seed).contains(&WeatherKind::Snow)));
+    }
+
+    #[test]
+    fn weather_lingers_more_often_than_it_turns() {
+        let hours = forecast(Season::Summer, 1);
+        let turns = hours.windows(2).filter(|pair| pair[0] != pair[1]).count();
+        assert!(turns > 0 && turns < hours.len() / 2, "{turns} turns");
+    }
+
+    #[test]
+    fn a_change_ramps_in_while_the_old_weather_fades() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut weather = Weather::default();
+        let changed = loop {
+            if let Some(changed) = weather.roll(Season::Autumn, &mut rng) {
+                break changed;
+            }
+        };
+        assert_eq!(changed.from, WeatherKind::Clear);
+        assert_eq!(changed.to, weather.kind);
+        assert_eq!(weather.previous, WeatherKind::Clear);
+        assert_eq!(weather.intensity, 0.0);
+        assert_eq!(weather.previous_intensity, 1.0);
+        assert!((0.4..=1.0).contains(&weather.peak));
+        assert_eq!(weather.dominant(), WeatherKind::Clear);
+
+        for _ in 0..TICKS_PER_HOUR {
+            weather.ramp();
+        }
+        assert_eq!(weather.intensity, weather.peak);
+        assert!(weather.previous_intensity < 1e-3);
+        assert_eq!(weather.dominant(), changed.to);
+    }
+}